@@ -11,6 +11,12 @@ pub struct DataCollector {
     data: Data,
 }
 
+impl Default for DataCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DataCollector {
     pub fn new() -> DataCollector {
         DataCollector {
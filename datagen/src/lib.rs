@@ -1,11 +1,18 @@
 pub mod datacollector;
 pub mod runner;
 
+use std::fmt;
+
 use game::{
     board::{Board, Status},
-    movegen::Move,
+    move_app::make_move,
+    movegen::{Move, MoveKind, PackedMove},
+    pgn::{GameRecord, GameResult, RecordedMove},
+};
+use serde::{
+    de::{self, SeqAccess, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize,
 };
-use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 pub struct Data {
@@ -14,11 +21,230 @@ pub struct Data {
 #[derive(Serialize, Deserialize)]
 pub struct Game {
     pub start: Board,
-    pub moves: Vec<Move>,
+    /// Older data stored full moves, they are packed on load
+    #[serde(deserialize_with = "read_moves")]
+    pub moves: Vec<PackedMove>,
+    /// Status of the final position for its side to move
     pub status: Status,
+    /// Search score of each move for the side that played it, empty in older data
+    #[serde(default)]
+    pub scores: Vec<i32>,
+}
+
+/// A move as a dataset stores it, packed, or in the full form of the first datasets: an array of
+/// `null`, `from`, `to` and `capture_square`
+struct StoredMove(PackedMove);
+
+impl<'de> Deserialize<'de> for StoredMove {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(StoredMoveVisitor)
+    }
+}
+
+struct StoredMoveVisitor;
+
+impl<'de> Visitor<'de> for StoredMoveVisitor {
+    type Value = StoredMove;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a packed move or an array of null, from, to and capture_square")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        u16::try_from(value)
+            .map(|packed| StoredMove(PackedMove(packed)))
+            .map_err(|_| E::invalid_value(Unexpected::Unsigned(value), &self))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let null = seq
+            .next_element::<bool>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let mut squares = [0; 3];
+        for (index, square) in squares.iter_mut().enumerate() {
+            *square = seq
+                .next_element::<u64>()?
+                .ok_or_else(|| de::Error::invalid_length(index + 1, &self))?;
+        }
+        let [from, to, capture_square] = squares;
+        // singles never had a from square
+        let kind = match (null, from) {
+            (true, _) => MoveKind::Pass,
+            (false, 0) => MoveKind::Single,
+            (false, _) => MoveKind::Double,
+        };
+        Ok(StoredMove(
+            Move {
+                kind,
+                from,
+                to,
+                capture_square,
+            }
+            .into(),
+        ))
+    }
+}
+
+fn read_moves<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PackedMove>, D::Error> {
+    let stored = Vec::<StoredMove>::deserialize(deserializer)?;
+    Ok(stored
+        .into_iter()
+        .map(|StoredMove(packed)| packed)
+        .collect())
+}
+
+impl Game {
+    /// The game as a PGN record, with the search scores as move comments
+    pub fn to_record(&self, event: &str) -> GameRecord {
+        let mut record = GameRecord::new(self.start);
+        record.event = event.to_string();
+        record.black = "sea_baxx".to_string();
+        record.white = "sea_baxx".to_string();
+        let mut board = self.start;
+        for (ply, packed) in self.moves.iter().enumerate() {
+            let mov = packed.unpack(&board);
+            make_move(&mut board, &mov);
+            record.moves.push(RecordedMove {
+                mov,
+                comment: self.scores.get(ply).map(i32::to_string),
+            });
+        }
+        record.result = GameResult::from_status(self.status, board.side_to_move);
+        record
+    }
+
+    /// A game from a PGN record, scores are only kept if every move has one
+    pub fn from_record(record: &GameRecord) -> Game {
+        let scores = record
+            .moves
+            .iter()
+            .map(RecordedMove::score)
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+        Game {
+            start: record.start,
+            moves: record
+                .moves
+                .iter()
+                .map(|recorded| recorded.mov.into())
+                .collect(),
+            status: record.result.status(record.final_board().side_to_move),
+            scores,
+        }
+    }
 }
 // Openings, contains a vector of opening board states.
 #[derive(Clone)]
 pub struct Openings {
     pub openings: Vec<Board>,
 }
+
+#[cfg(test)]
+mod tests {
+    use game::{
+        board::{Board, Side, Status},
+        move_app::make_move,
+        movegen::{generate_moves, MoveKind, PackedMove},
+    };
+    use serde::{Deserialize, Serialize};
+
+    use crate::Game;
+
+    /// `Board` as the first datasets stored it, before it had a hash or a geometry
+    #[derive(Serialize)]
+    struct BaselineBoard {
+        blockers: u64,
+        boards: [u64; 2],
+        side_to_move: Side,
+        half_move: u8,
+        full_move: u32,
+    }
+
+    /// `Move` as the first datasets stored it, singles had no from square
+    #[derive(Serialize)]
+    struct BaselineMove {
+        null: bool,
+        from: u64,
+        to: u64,
+        capture_square: u64,
+    }
+
+    #[derive(Serialize)]
+    struct BaselineGame {
+        start: BaselineBoard,
+        moves: Vec<BaselineMove>,
+        status: Status,
+    }
+
+    #[test]
+    fn reads_baseline_games() {
+        // black is walled in, so the game has passes, singles and doubles
+        let start = Board::new("7/7/7/7/ooooooo/ooooooo/xxxxxxx x 5 3".to_string());
+        let mut board = start;
+        let mut moves = vec![];
+        for kind in [
+            MoveKind::Pass,
+            MoveKind::Single,
+            MoveKind::Pass,
+            MoveKind::Double,
+        ] {
+            let mov = *generate_moves(&board)
+                .iter()
+                .find(|mov| mov.kind == kind)
+                .unwrap();
+            make_move(&mut board, &mov);
+            moves.push(mov);
+        }
+
+        let baseline = BaselineGame {
+            start: BaselineBoard {
+                blockers: start.blockers,
+                boards: start.boards,
+                side_to_move: start.side_to_move,
+                half_move: start.half_move,
+                full_move: start.full_move,
+            },
+            moves: moves
+                .iter()
+                .map(|mov| BaselineMove {
+                    null: mov.kind == MoveKind::Pass,
+                    from: if mov.kind == MoveKind::Double {
+                        mov.from
+                    } else {
+                        0
+                    },
+                    to: mov.to,
+                    capture_square: mov.capture_square,
+                })
+                .collect(),
+            status: Status::Ongoing,
+        };
+        let bytes = rmp_serde::to_vec(&baseline).unwrap();
+
+        let game = Game::deserialize(&mut rmp_serde::Deserializer::new(&bytes[..])).unwrap();
+        assert_eq!(game.start, start);
+        assert_eq!(game.start.hash, start.compute_hash());
+        assert_eq!(
+            game.moves,
+            moves.into_iter().map(PackedMove::from).collect::<Vec<_>>()
+        );
+        assert_eq!(game.status, Status::Ongoing);
+        assert!(game.scores.is_empty());
+
+        // games written now still load
+        let bytes = rmp_serde::to_vec(&game).unwrap();
+        let reloaded = Game::deserialize(&mut rmp_serde::Deserializer::new(&bytes[..])).unwrap();
+        assert_eq!(reloaded.moves, game.moves);
+    }
+
+    #[test]
+    fn hash_is_not_stored() {
+        let mut board = Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string());
+        let bytes = rmp_serde::to_vec(&board).unwrap();
+        // a wrong key in memory never makes it to disk
+        board.hash ^= 1;
+        assert_eq!(rmp_serde::to_vec(&board).unwrap(), bytes);
+        let loaded = Board::deserialize(&mut rmp_serde::Deserializer::new(&bytes[..])).unwrap();
+        assert_eq!(loaded.hash, loaded.compute_hash());
+    }
+}
@@ -8,9 +8,15 @@ use std::{
 };
 
 use clap::{Args, Parser, Subcommand};
-use datagen::{datacollector::DataCollector, runner::Runner, Data, Openings};
-use game::board::Board;
-use serde::Deserialize;
+use datagen::{datacollector::DataCollector, runner::Runner, Data, Game, Openings};
+use game::{
+    board::Board,
+    geometry::Geometry,
+    pgn::read_games,
+    random::{random_position, Rng},
+};
+use search::SEARCH_STACK_SIZE;
+use serde::{Deserialize, Serialize};
 
 #[derive(Parser)]
 struct Cli {
@@ -21,6 +27,17 @@ struct Cli {
 enum Commands {
     Generate(GenerateArgs),
     Information(InformationArgs),
+    /// Write the games of a data file as PGN
+    ToPgn(ConvertArgs),
+    /// Read PGN games into a data file
+    FromPgn(ConvertArgs),
+}
+#[derive(Args)]
+struct ConvertArgs {
+    #[arg(short, long)]
+    input: String,
+    #[arg(short, long)]
+    output: String,
 }
 #[derive(Args)]
 struct InformationArgs {
@@ -29,9 +46,29 @@ struct InformationArgs {
 }
 #[derive(Args)]
 struct GenerateArgs {
-    /// Name of the person to greet
+    /// Path to a file of opening FENs, one per line
     #[arg(short, long)]
-    openings_path: String,
+    openings_path: Option<String>,
+
+    /// Number of random openings to generate on random gap layouts, on top of the openings file
+    #[arg(long, default_value_t = 0)]
+    random_openings: u32,
+
+    /// Number of random moves played to reach each random opening
+    #[arg(long, default_value_t = 4)]
+    random_plies: u32,
+
+    /// Chance of each square being a gap in random openings
+    #[arg(long, default_value_t = 0.1)]
+    gap_density: f64,
+
+    /// Number of files and ranks of the board random openings are played on, from 3 to 7
+    #[arg(long, default_value_t = 7)]
+    board_size: u8,
+
+    /// Seed for the random openings
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
 
     /// Amount of data to generate, in terms of games
     #[arg(short, long, default_value_t = 1000)]
@@ -61,13 +98,46 @@ fn main() {
         Commands::Generate(args) => {
             // grab all openings
             let mut openings = Openings { openings: vec![] };
-            if let Ok(lines) = read_lines(&args.openings_path) {
+            if let Some(openings_path) = &args.openings_path {
+                let Ok(lines) = read_lines(openings_path) else {
+                    panic!("Openings not found at path {}", openings_path);
+                };
                 // Consumes the iterator, returns an (Optional) String
-                for line in lines.flatten() {
-                    openings.openings.push(Board::new(line));
+                for (number, line) in lines.map_while(Result::ok).enumerate() {
+                    // skip blank lines and comments
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+                    match Board::from_fen(line) {
+                        Ok(board) => openings.openings.push(board),
+                        Err(err) => eprintln!("Skipping opening on line {}: {}", number + 1, err),
+                    }
                 }
-            } else {
-                panic!("Openings not found at path {}", args.openings_path);
+            }
+            // a 2x2 board is full from the start and a 1x1 one has no room for both sides
+            let geometry =
+                Geometry::new(args.board_size, args.board_size).filter(|_| args.board_size >= 3);
+            let Some(geometry) = geometry else {
+                panic!(
+                    "Board size must be between 3 and 7, got {}",
+                    args.board_size
+                );
+            };
+            let mut rng = Rng::new(args.seed);
+            for _ in 0..args.random_openings {
+                let Some(opening) =
+                    random_position(&mut rng, geometry, args.random_plies, args.gap_density)
+                else {
+                    panic!(
+                        "No game on a {0}x{0} board lasts {1} random plies, pass fewer",
+                        args.board_size, args.random_plies
+                    );
+                };
+                openings.openings.push(opening);
+            }
+            if openings.openings.is_empty() {
+                panic!("No openings, pass an openings path or a number of random openings");
             }
 
             // depth to reach: 7 (chosen because we can hit this depth within ~100 ms rn)
@@ -94,10 +164,13 @@ fn main() {
                 let x = openings.clone();
                 let y = num_games_left.clone();
                 let s = send.clone();
-                thread::spawn(move || {
-                    let mut runner = Runner::new(x);
-                    runner.start(id as usize, y, s);
-                });
+                thread::Builder::new()
+                    .stack_size(SEARCH_STACK_SIZE)
+                    .spawn(move || {
+                        let mut runner = Runner::new(x);
+                        runner.start(id as usize, y, s);
+                    })
+                    .expect("unable to spawn runner thread");
             }
             let mut old = *num_games_left.lock().unwrap();
             let t0 = Instant::now();
@@ -164,5 +237,29 @@ fn main() {
                     .unwrap()
             );
         }
+        Commands::ToPgn(args) => {
+            let thing = fs::read(Path::new(&args.input)).unwrap();
+            let data = <Data>::deserialize(&mut rmp_serde::Deserializer::new(&thing[..])).unwrap();
+            let pgn = data
+                .boards
+                .iter()
+                .map(|game| game.to_record("datagen").to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            fs::write(&args.output, pgn).expect("unable to write pgn");
+            println!("Wrote {} games", data.boards.len());
+        }
+        Commands::FromPgn(args) => {
+            let pgn = fs::read_to_string(&args.input).unwrap();
+            let records = read_games(&pgn).unwrap_or_else(|err| panic!("Invalid pgn: {err}"));
+            let data = Data {
+                boards: records.iter().map(Game::from_record).collect(),
+            };
+            let mut buf = vec![];
+            data.serialize(&mut rmp_serde::Serializer::new(&mut buf))
+                .unwrap();
+            fs::write(&args.output, buf).expect("unable to write data");
+            println!("Wrote {} games", data.boards.len());
+        }
     }
 }
@@ -1,10 +1,6 @@
-use std::{
-    cmp::Ordering,
-    collections::HashMap,
-    sync::{atomic::AtomicU64, mpsc::Sender, Arc, Mutex},
-};
+use std::sync::{mpsc::Sender, Arc, Mutex};
 
-use game::{board::Status, move_app::make_move};
+use game::{board::Status, position::Position};
 use rand::{seq::SliceRandom, thread_rng};
 use search::{GoInfo, Search, Shared};
 
@@ -18,11 +14,15 @@ pub struct Runner {
 
 impl Runner {
     pub fn new(openings: Openings) -> Runner {
-        let val = Arc::new(Mutex::new(Shared { stop: false }));
+        let val = Arc::new(Shared::default());
+        let searcher1 = Search::new(val.clone());
+        // both sides search the same game, so they can share what they find
+        let mut searcher2 = Search::new(val.clone());
+        searcher2.set_table(Arc::clone(searcher1.table()));
         Runner {
-            searcher1: Search::new(val.clone()),
-            searcher2: Search::new(val.clone()),
-            openings: openings,
+            searcher1,
+            searcher2,
+            openings,
         }
     }
 
@@ -44,30 +44,26 @@ impl Runner {
         self.openings.openings.shuffle(&mut rng);
         while *num_games_left.lock().unwrap() != 0 {
             let mut moves = vec![];
-            let mut game_state =
-                self.openings.openings[current_opening % self.openings.openings.len()];
-            let picker = (current_opening / self.openings.openings.len()) % 2 == 0;
+            let mut scores = vec![];
+            let mut game_state = Position::new(
+                self.openings.openings[current_opening % self.openings.openings.len()],
+            );
+            let picker = (current_opening / self.openings.openings.len()).is_multiple_of(2);
             self.searcher1.setup_newgame();
             self.searcher2.setup_newgame();
             let mut x = 0;
-            let mut fold_detector = HashMap::new();
-            let mut draw = false;
             let mut discard = false;
             // println!("Game started {id}");
 
             while game_state.status() == Status::Ongoing {
-                if x % 30 == 0 {
-                    // println!("{id}: {x}");
-                }
-
                 if x == 700 {
                     discard = true;
                     // println!("Game discarded! id: {id}");
                     break;
                 }
                 x += 1;
-                let (best_move, _) = if picker {
-                    match game_state.side_to_move {
+                let (best_move, score) = if picker {
+                    match game_state.board().side_to_move {
                         game::board::Side::Black => {
                             self.searcher1.set_position_direct(&game_state);
                             self.searcher1.find_best_move(false, &go_info)
@@ -78,7 +74,7 @@ impl Runner {
                         }
                     }
                 } else {
-                    match game_state.side_to_move {
+                    match game_state.board().side_to_move {
                         game::board::Side::White => {
                             self.searcher1.set_position_direct(&game_state);
                             self.searcher1.find_best_move(false, &go_info)
@@ -90,35 +86,22 @@ impl Runner {
                     }
                 };
 
-                make_move(&mut game_state, &best_move);
-                if fold_detector.contains_key(&game_state.clone()) {
-                    *fold_detector.get_mut(&game_state.clone()).unwrap() += 1;
-                } else {
-                    fold_detector.insert(game_state, 1);
-                }
-
-                moves.push(best_move);
-                if let Some(val) = fold_detector.get(&game_state) {
-                    if *val == 3 {
-                        draw = true;
-                        break;
-                    }
-                }
+                game_state.make(&best_move);
+                moves.push(best_move.into());
+                scores.push(score);
             }
             if discard {
                 current_opening += 1;
                 continue;
             }
-            let status = if draw {
-                Status::Draw
-            } else {
-                game_state.status()
-            };
+            // the position reports threefold repetitions as draws
+            let status = game_state.status();
             if send_pipe
                 .send(Game {
                     start: self.openings.openings[current_opening % self.openings.openings.len()],
                     moves,
                     status,
+                    scores,
                 })
                 .is_err()
             {
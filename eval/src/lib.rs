@@ -2,6 +2,12 @@ use game::{board::Board, movegen::singles};
 
 pub struct Eval {}
 
+impl Default for Eval {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Eval {
     pub fn new() -> Self {
         Eval {}
@@ -11,7 +17,7 @@ impl Eval {
         let us = board.boards[board.side_to_move as usize];
         let them = board.boards[1 - board.side_to_move as usize];
         let piece_count = us.count_ones() as i32 - them.count_ones() as i32;
-        let mobilty =
+        let _mobility =
             (singles(us) & !us).count_ones() as i32 - (singles(them) & !them).count_ones() as i32;
 
         piece_count
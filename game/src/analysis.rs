@@ -0,0 +1,190 @@
+//! Bitboard features of a position for evaluation experiments.
+//!
+//! Everything here is built on [`singles`] and [`doubles`], and squares off a board smaller than
+//! 7x7 are treated like gaps.
+
+use crate::{
+    board::{BitBoard, Board, Side},
+    geometry::Geometry,
+    movegen::{doubles, singles},
+};
+
+/// Stones next to at least one empty square.
+///
+/// These are the only stones a capture could flip, but only if an enemy stone can reach one of
+/// those empty squares with a single or double move.
+pub fn frontier(stones: BitBoard, empty: BitBoard) -> BitBoard {
+    stones & singles(empty)
+}
+
+/// Stones that can never be captured, the largest set of `stones` whose neighbours are all in
+/// the set, `walls` or off the board.
+///
+/// No neighbour of such a stone can ever become empty for the opponent to land on, unless their
+/// own side moves one of them away with a double move. `walls` are the gaps and any other
+/// squares that never hold a stone.
+pub fn stable(stones: BitBoard, walls: BitBoard) -> BitBoard {
+    let mut stable = stones;
+    loop {
+        // squares that could be empty or hold an enemy stone at some point
+        let open = Geometry::STANDARD.mask() & !(stable | walls);
+        let next = stable & !singles(open);
+        if next == stable {
+            return stable;
+        }
+        stable = next;
+    }
+}
+
+/// Squares a side with `stones` can move to on an empty board, one or two steps away
+pub fn reach(stones: BitBoard) -> BitBoard {
+    singles(stones) | doubles(stones)
+}
+
+/// The connected areas of `empty`, where squares are connected if they are one step apart
+pub fn regions(empty: BitBoard) -> Regions {
+    Regions(empty & Geometry::STANDARD.mask())
+}
+
+/// Iterator over the regions of a set of empty squares, lowest square first, see [`regions`]
+#[derive(Clone, Copy, Debug)]
+pub struct Regions(BitBoard);
+
+impl Iterator for Regions {
+    type Item = BitBoard;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+        // flood fill from the lowest square
+        let mut region = self.0 & self.0.wrapping_neg();
+        loop {
+            let grown = (region | singles(region)) & self.0;
+            if grown == region {
+                break;
+            }
+            region = grown;
+        }
+        self.0 &= !region;
+        Some(region)
+    }
+}
+
+/// Number of regions of `empty` with an odd number of squares
+pub fn odd_regions(empty: BitBoard) -> u32 {
+    regions(empty)
+        .filter(|region| region.count_ones() % 2 == 1)
+        .count() as u32
+}
+
+impl Board {
+    /// Squares that are gaps or off the board
+    fn walls(&self) -> BitBoard {
+        self.blockers | !self.geometry.mask()
+    }
+
+    /// Stones of `side` next to an empty square, see [`frontier`]
+    pub fn frontier(&self, side: Side) -> BitBoard {
+        frontier(self.boards[side as usize], self.empty())
+    }
+
+    /// Stones of `side` that can never be captured, see [`stable`]
+    pub fn stable(&self, side: Side) -> BitBoard {
+        stable(self.boards[side as usize], self.walls())
+    }
+
+    /// The empty squares of regions that only one side can move into right now, indexed by side
+    pub fn exclusive_regions(&self) -> [BitBoard; 2] {
+        let reach = self.boards.map(reach);
+        let mut exclusive = [0; 2];
+        for region in regions(self.empty()) {
+            match (reach[0] & region != 0, reach[1] & region != 0) {
+                (true, false) => exclusive[0] |= region,
+                (false, true) => exclusive[1] |= region,
+                _ => {}
+            }
+        }
+        exclusive
+    }
+
+    /// Number of empty regions with an odd number of squares, see [`odd_regions`]
+    pub fn odd_regions(&self) -> u32 {
+        odd_regions(self.empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{BitBoard, Board, Side},
+        square::Square,
+    };
+
+    use super::{frontier, regions};
+
+    fn squares(names: &[&str]) -> BitBoard {
+        names.iter().fold(0, |bb, name| {
+            bb | name.parse::<Square>().unwrap().bitboard()
+        })
+    }
+
+    #[test]
+    fn frontier_stones() {
+        let board = Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string());
+        assert_eq!(board.frontier(Side::Black), squares(&["a7", "g1"]));
+        assert_eq!(board.frontier(Side::White), squares(&["g7", "a1"]));
+
+        // a1 is boxed in by its own stones
+        let board = Board::new("7/7/7/7/7/xx5/xx5 x 0 1".to_string());
+        assert_eq!(board.frontier(Side::Black), squares(&["a2", "b2", "b1"]));
+        assert_eq!(frontier(board.boards[1], board.empty()), 0);
+    }
+
+    #[test]
+    fn stable_stones() {
+        // a7, b7 and a6 only touch each other and gaps
+        let board = Board::new("xx-4/x--4/--5/7/7/7/o5x x 0 1".to_string());
+        assert_eq!(board.stable(Side::Black), squares(&["a7", "b7", "a6"]));
+        assert_eq!(board.stable(Side::White), 0);
+
+        // the inner stones of a block are not stable while the outer ones can be flipped and moved
+        let board = Board::new("xxx4/xxx4/xxx4/7/7/7/o5x x 0 1".to_string());
+        assert_eq!(board.stable(Side::Black), 0);
+
+        // on a 4x4 board the edges and gaps wall in the corner block
+        let board = Board::new("xx2/xx2/4/3o x 0 1".to_string());
+        assert_eq!(board.stable(Side::Black), 0);
+        let board = Board::new("xx--/xx--/----/3o x 0 1".to_string());
+        assert_eq!(
+            board.stable(Side::Black),
+            squares(&["a4", "b4", "a3", "b3"])
+        );
+
+        let full =
+            Board::new("xxxxxxx/xxxxxxx/xxxxxxx/xxxxxxx/xxxxxxx/xxxxxxx/xxxxxxx o 0 1".to_string());
+        assert_eq!(full.stable(Side::Black), full.boards[0]);
+    }
+
+    #[test]
+    fn empty_regions() {
+        let board = Board::new("x6/7/7/-------/7/7/o6 x 0 1".to_string());
+        let found = regions(board.empty()).collect::<Vec<_>>();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].count_ones(), 20);
+        assert_eq!(found[0] | found[1], board.empty());
+        assert_eq!(board.exclusive_regions(), [found[1], found[0]]);
+        assert_eq!(board.odd_regions(), 0);
+
+        // both sides can reach both regions, and the top one has an odd size
+        let board = Board::new("x5o/7/7/-------/7/7/o4-x x 0 1".to_string());
+        assert_eq!(regions(board.empty()).count(), 2);
+        assert_eq!(board.exclusive_regions(), [0, 0]);
+        assert_eq!(board.odd_regions(), 1);
+
+        // regions on smaller boards stay on the board
+        let board = Board::new("x2/1-1/2o x 0 1".to_string());
+        assert_eq!(regions(board.empty()).collect::<Vec<_>>(), [board.empty()]);
+        assert_eq!(board.odd_regions(), 0);
+    }
+}
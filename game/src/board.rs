@@ -1,16 +1,60 @@
-use std::ops::Not;
+use std::{error::Error, fmt::Display, ops::Not};
 
 use serde::{Deserialize, Serialize};
 
-use crate::movegen::singles;
+use crate::{
+    geometry::{Geometry, MAX_SIZE},
+    movegen::{generate_moves_into, Move, MoveKind, DOUBLE_JUMPS, NEIGHBOURS},
+    movelist::MoveList,
+    rules,
+    square::Square,
+    zobrist,
+};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, Hash)]
+#[serde(from = "StoredBoard")]
 pub struct Board {
     pub blockers: BitBoard,
     pub boards: [BitBoard; 2],
     pub side_to_move: Side,
     pub half_move: u8,
     pub full_move: u32,
+    /// Zobrist key of the position, kept up to date by `make_move` and `unmake_move`.
+    ///
+    /// Never stored, the keys are an implementation detail, so it is recomputed on load.
+    #[serde(skip)]
+    pub hash: u64,
+    /// Size of the board, data written before boards could vary in size is 7x7
+    #[serde(default)]
+    pub geometry: Geometry,
+}
+
+/// A [`Board`] as it is serialized, without the hash
+#[derive(Deserialize)]
+struct StoredBoard {
+    blockers: BitBoard,
+    boards: [BitBoard; 2],
+    side_to_move: Side,
+    half_move: u8,
+    full_move: u32,
+    #[serde(default)]
+    geometry: Geometry,
+}
+
+impl From<StoredBoard> for Board {
+    fn from(stored: StoredBoard) -> Self {
+        let mut board = Board {
+            blockers: stored.blockers,
+            boards: stored.boards,
+            side_to_move: stored.side_to_move,
+            half_move: stored.half_move,
+            full_move: stored.full_move,
+            hash: 0,
+            geometry: stored.geometry,
+        };
+        board.hash = board.compute_hash();
+        board
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, Hash)]
@@ -80,121 +124,348 @@ impl Side {
 
 pub type BitBoard = u64;
 
+/// Reasons an Ataxx FEN string can fail to parse.
+///
+/// Ranks and files are zero indexed from a1, so `rank: 6, file: 2` is the c7 square.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FenError {
+    /// The input contained no fields at all
+    Empty,
+    /// The board field has more than 7 ranks
+    RankCount(usize),
+    /// A character that is not a stone, gap, digit or rank separator
+    InvalidCharacter { rank: u8, file: u8, character: char },
+    /// A rank describes more squares than the top rank, or more than 7
+    RankTooLong { rank: u8, expected: u8 },
+    /// A rank describes fewer squares than the top rank, or none at all
+    RankTooShort { rank: u8, files: u8, expected: u8 },
+    /// A square is claimed by more than one of black, white and the gaps
+    OverlappingPieces(BitBoard),
+    /// The side to move field is absent
+    MissingSideToMove,
+    /// The side to move field is neither `x` nor `o`
+    InvalidSideToMove(String),
+    /// The half move counter is not a number in range
+    InvalidHalfMove(String),
+    /// The full move counter is not a number in range
+    InvalidFullMove(String),
+    /// There is more text after the full move counter
+    TrailingField(String),
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let file_name = |file: u8| (b'a' + file) as char;
+        match self {
+            FenError::Empty => write!(f, "empty fen"),
+            FenError::RankCount(count) => {
+                write!(f, "expected at most {MAX_SIZE} ranks, found {count}")
+            }
+            FenError::InvalidCharacter {
+                rank,
+                file,
+                character,
+            } => write!(
+                f,
+                "invalid character '{character}' at {}{}",
+                file_name(*file),
+                rank + 1
+            ),
+            FenError::RankTooLong { rank, expected } => {
+                write!(f, "rank {} has more than {expected} files", rank + 1)
+            }
+            FenError::RankTooShort {
+                rank,
+                files,
+                expected,
+            } => write!(
+                f,
+                "rank {} has {files} files, expected {expected}",
+                rank + 1
+            ),
+            FenError::OverlappingPieces(overlap) => {
+                write!(f, "squares {overlap:#x} are occupied more than once")
+            }
+            FenError::MissingSideToMove => write!(f, "missing side to move"),
+            FenError::InvalidSideToMove(side) => {
+                write!(f, "invalid side to move '{side}', expected x or o")
+            }
+            FenError::InvalidHalfMove(counter) => {
+                write!(f, "invalid half move counter '{counter}'")
+            }
+            FenError::InvalidFullMove(counter) => {
+                write!(f, "invalid full move counter '{counter}'")
+            }
+            FenError::TrailingField(field) => write!(f, "unexpected trailing field '{field}'"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// Reasons a move string can be rejected by [`Board::parse_move`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MoveError {
+    /// The move is not `0000`, a destination square or a from and to square
+    InvalidFormat(String),
+    /// A square is not on the board
+    InvalidSquare(String),
+    /// The game is already over so no move can be played
+    GameOver,
+    /// `0000` was given but the side to move has legal moves
+    IllegalPass,
+    /// The from square of a double move does not hold a stone of the side to move
+    NotOurStone(Square),
+    /// The destination square is not empty
+    Occupied(Square),
+    /// The destination is not within two squares of the from square
+    OutOfRange { from: Square, to: Square },
+    /// No stone of the side to move is adjacent to the destination of a single move
+    NoAdjacentStone(Square),
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::InvalidFormat(mov) => write!(f, "invalid move format '{mov}'"),
+            MoveError::InvalidSquare(square) => write!(f, "invalid square '{square}'"),
+            MoveError::GameOver => write!(f, "the game is over"),
+            MoveError::IllegalPass => write!(f, "cannot pass when there are legal moves"),
+            MoveError::NotOurStone(square) => {
+                write!(f, "{square} does not hold a stone of the side to move")
+            }
+            MoveError::Occupied(square) => write!(f, "{square} is not empty"),
+            MoveError::OutOfRange { from, to } => write!(f, "{to} is out of range of {from}"),
+            MoveError::NoAdjacentStone(square) => {
+                write!(f, "no stone of the side to move is next to {square}")
+            }
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_fen())
+    }
+}
+
 impl Board {
+    /// Parse a board from a FEN string, panicking if it is malformed.
+    ///
+    /// Prefer [`Board::from_fen`] for anything that comes from outside the engine.
     pub fn new(fen: String) -> Board {
+        Board::from_fen(&fen).unwrap_or_else(|err| panic!("Invalid fen \"{fen}\": {err}"))
+    }
+
+    /// Parse a board from a FEN string such as `x5o/7/7/7/7/7/o5x x 0 1`.
+    ///
+    /// The size of the board is taken from the number of ranks and the width of the top rank, so
+    /// `x3o/5/5/5/o3x x` is the 5x5 starting position. The half and full move counters are
+    /// optional and default to `0` and `1`.
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
         let mut out = Board {
             blockers: 0,
             boards: [0; 2],
             side_to_move: Side::Black,
             half_move: 0,
-            full_move: 0,
+            full_move: 1,
+            hash: 0,
+            geometry: Geometry::STANDARD,
         };
 
-        let parts = fen.split(' ').collect::<Vec<&str>>();
+        let mut parts = fen.split_whitespace();
 
         // get all the board info (blockers, player 1 board, player 2 board)
-
-        // current board spot (starts at a8)
-        let mut current_board_index = 48;
-        // iteratate over board fen
-        for text in parts[0].chars() {
-            match text {
-                'x' => {
-                    out.boards[0] |= 1 << current_board_index;
-                    current_board_index += 1;
-                }
-                'o' => {
-                    out.boards[1] |= 1 << current_board_index;
-                    current_board_index += 1;
-                }
-                '-' => {
-                    out.blockers |= 1 << current_board_index;
-                    current_board_index += 1;
-                }
-                '/' => {
-                    current_board_index -= 15;
+        let ranks = parts.next().ok_or(FenError::Empty)?.split('/');
+        let rank_count = ranks.clone().count();
+        if rank_count > MAX_SIZE as usize {
+            return Err(FenError::RankCount(rank_count));
+        }
+        // ranks are listed from the top rank down to rank 1, the top rank sets the width
+        let mut width = None;
+        for (rank, text) in (0..rank_count as u8).rev().zip(ranks) {
+            let expected = width.unwrap_or(MAX_SIZE);
+            let mut file = 0;
+            for character in text.chars() {
+                if file >= expected {
+                    return Err(FenError::RankTooLong { rank, expected });
                 }
-                _ => {
-                    let value = String::from(text)
-                        .parse::<u8>()
-                        .unwrap_or_else(|_| panic!("Invalid character {}", text));
-                    current_board_index += value;
+                let square = Square::new(file, rank).bitboard();
+                match character {
+                    'x' => out.boards[0] |= square,
+                    'o' => out.boards[1] |= square,
+                    '-' => out.blockers |= square,
+                    '1'..='7' => {
+                        file += character as u8 - b'1';
+                        if file >= expected {
+                            return Err(FenError::RankTooLong { rank, expected });
+                        }
+                    }
+                    _ => {
+                        return Err(FenError::InvalidCharacter {
+                            rank,
+                            file,
+                            character,
+                        })
+                    }
                 }
+                file += 1;
+            }
+            if file == 0 || (width.is_some() && file != expected) {
+                return Err(FenError::RankTooShort {
+                    rank,
+                    files: file,
+                    expected: width.unwrap_or(1),
+                });
             }
+            width = Some(file);
         }
+        out.geometry = Geometry::new(width.unwrap_or(0), rank_count as u8)
+            .expect("rank count and width are checked while parsing");
+        let overlap = (out.boards[0] & out.boards[1])
+            | (out.boards[0] & out.blockers)
+            | (out.boards[1] & out.blockers);
+        if overlap != 0 {
+            return Err(FenError::OverlappingPieces(overlap));
+        }
+
         // get side to move
-        out.side_to_move = Side::from(parts[1]);
+        out.side_to_move = match parts.next() {
+            Some("x") => Side::Black,
+            Some("o") => Side::White,
+            Some(side) => return Err(FenError::InvalidSideToMove(side.to_string())),
+            None => return Err(FenError::MissingSideToMove),
+        };
         // get half move counter
-        out.half_move = String::from(parts[2]).parse::<u8>().unwrap_or_else(|_| {
-            panic!(
-                "Unexpected substring when parsing half_move counter :{}",
-                parts[2]
-            )
-        });
+        if let Some(half_move) = parts.next() {
+            out.half_move = half_move
+                .parse::<u8>()
+                .map_err(|_| FenError::InvalidHalfMove(half_move.to_string()))?;
+        }
         // get full move counter
-        out.full_move = String::from(parts[2]).parse::<u32>().unwrap_or_else(|_| {
-            panic!(
-                "Unexpected substring when parsing full move counter :{}",
-                parts[3]
-            )
-        });
-        out
-    }
-
-    pub fn game_over(&self) -> bool {
-        let both = self.current_pieces() | self.other_pieces();
-        let moves = singles(singles(both));
-
-        if self.current_pieces() == 0 {
-            return true;
+        if let Some(full_move) = parts.next() {
+            out.full_move = full_move
+                .parse::<u32>()
+                .map_err(|_| FenError::InvalidFullMove(full_move.to_string()))?;
         }
-
-        if self.other_pieces() == 0 {
-            return true;
+        if let Some(field) = parts.next() {
+            return Err(FenError::TrailingField(field.to_string()));
         }
+        out.hash = out.compute_hash();
 
-        if self.half_move >= 100 {
-            return true;
-        }
+        Ok(out)
+    }
 
-        if moves & self.empty() != 0 {
-            return false;
+    /// Serialize the board into the same FEN format accepted by [`Board::from_fen`].
+    pub fn to_fen(&self) -> String {
+        let mut out = String::new();
+        for rank in (0..self.geometry.ranks()).rev() {
+            let mut empty = 0;
+            for file in 0..self.geometry.files() {
+                let square = Square::new(file, rank).bitboard();
+                let character = if self.boards[0] & square != 0 {
+                    'x'
+                } else if self.boards[1] & square != 0 {
+                    'o'
+                } else if self.blockers & square != 0 {
+                    '-'
+                } else {
+                    empty += 1;
+                    continue;
+                };
+                if empty != 0 {
+                    out.push(char::from(b'0' + empty));
+                    empty = 0;
+                }
+                out.push(character);
+            }
+            if empty != 0 {
+                out.push(char::from(b'0' + empty));
+            }
+            if rank != 0 {
+                out.push('/');
+            }
         }
-
-        true
+        let side = match self.side_to_move {
+            Side::Black => 'x',
+            Side::White => 'o',
+        };
+        format!("{out} {side} {} {}", self.half_move, self.full_move)
     }
-    pub fn status(&self) -> Status {
-        let _current = self.side_to_move;
-        let _other = !self.side_to_move;
-        let both = self.current_pieces() | self.other_pieces();
-        let moves = singles(singles(both));
 
-        if self.current_pieces() == 0 {
-            // side to move has no pieces
-            return Status::Loser;
+    /// Parse a move in UAI notation (`b2`, `a1c3` or `0000`) and check that it is legal in this position.
+    ///
+    /// A four character move between adjacent squares is read as the single move to its destination.
+    pub fn parse_move(&self, input: &str) -> Result<Move, MoveError> {
+        if self.game_over() {
+            return Err(MoveError::GameOver);
         }
-
-        if self.other_pieces() == 0 {
-            // other person has no pieces
-            return Status::Winner;
+        let mut moves = MoveList::new();
+        generate_moves_into(self, &mut moves);
+        if input == "0000" {
+            return if moves[0].kind == MoveKind::Pass {
+                Ok(Move::PASS)
+            } else {
+                Err(MoveError::IllegalPass)
+            };
         }
 
-        if self.half_move >= 100 {
-            // 50 move rule
-            return Status::Draw;
+        let parse_square = |square: &str| {
+            square
+                .parse::<Square>()
+                .map_err(|err| MoveError::InvalidSquare(err.0))
+        };
+        let (from, to) = match input.len() {
+            2 => (None, parse_square(input)?),
+            4 if input.is_char_boundary(2) => (
+                Some(parse_square(&input[0..2])?),
+                parse_square(&input[2..4])?,
+            ),
+            _ => return Err(MoveError::InvalidFormat(input.to_string())),
+        };
+        for square in from.iter().chain([&to]) {
+            if !self.geometry.contains(*square) {
+                return Err(MoveError::InvalidSquare(square.to_string()));
+            }
         }
-
-        if moves & self.empty() != 0 {
-            // side to move still has moves left
-            return Status::Ongoing;
+        if to.bitboard() & self.empty() == 0 {
+            return Err(MoveError::Occupied(to));
         }
+        let (kind, from) = match from {
+            Some(from) if from.bitboard() & self.current_pieces() == 0 => {
+                return Err(MoveError::NotOurStone(from))
+            }
+            Some(from) if NEIGHBOURS[from.index()] & to.bitboard() != 0 => (MoveKind::Single, 0),
+            Some(from) if DOUBLE_JUMPS[from.index()] & to.bitboard() != 0 => {
+                (MoveKind::Double, from.bitboard())
+            }
+            Some(from) => return Err(MoveError::OutOfRange { from, to }),
+            None if NEIGHBOURS[to.index()] & self.current_pieces() == 0 => {
+                return Err(MoveError::NoAdjacentStone(to))
+            }
+            None => (MoveKind::Single, 0),
+        };
 
-        if self.current_pieces().count_ones() > self.other_pieces().count_ones() {
-            Status::Winner
-        } else {
-            Status::Loser
-        }
+        Ok(Move {
+            kind,
+            from,
+            to: to.bitboard(),
+            capture_square: NEIGHBOURS[to.index()] & self.other_pieces(),
+        })
+    }
+
+    /// Is the game over, see [`rules`] for the termination rules
+    pub fn game_over(&self) -> bool {
+        rules::game_over(self)
+    }
+
+    /// Result of the game from the point of view of the side to move
+    pub fn status(&self) -> Status {
+        rules::status(self)
     }
+
     pub fn current_pieces(&self) -> u64 {
         self.boards[self.side_to_move as usize]
     }
@@ -207,11 +478,229 @@ impl Board {
         self.boards[0] | self.boards[1] | self.blockers
     }
 
+    /// Empty squares on the board
     pub fn empty(&self) -> u64 {
-        !self.blockers()
+        !self.blockers() & self.geometry.mask()
     }
 
     pub fn zobrist_hash(&self) -> u64 {
-        self.boards[0] | self.boards[1]
+        self.hash
+    }
+
+    /// Compute the zobrist key of the position from scratch
+    pub fn compute_hash(&self) -> u64 {
+        // squares off a smaller board hash like gaps, the game is the same either way
+        let off_board = !self.geometry.mask() & Geometry::STANDARD.mask();
+        let mut hash = zobrist::stone_keys(Side::Black, self.boards[0])
+            ^ zobrist::stone_keys(Side::White, self.boards[1])
+            ^ zobrist::gap_keys(self.blockers | off_board);
+        if self.side_to_move == Side::White {
+            hash ^= zobrist::SIDE_KEY;
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{move_app::make_move, movegen::generate_moves};
+
+    use super::{Board, FenError, Move, MoveError, Side, Square};
+
+    fn openings() -> impl Iterator<Item = &'static str> {
+        include_str!("../../openings/ply3_fair.txt")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for fen in openings().chain([
+            "x5o/7/7/7/7/7/o5x x 0 1",
+            "x5o/7/2-1-2/3-3/2-1-2/7/o5x o 17 9",
+            "7/7/7/7/-------/-------/x5o x 0 1",
+            "7/7/7/7/ooooooo/ooooooo/xxxxxxx o 100 250",
+            "x3o/5/5/5/o3x x 0 1",
+            "x4o/6/2--2/6/6/o4x o 3 2",
+            "x1o/3/o1x x 0 1",
+            "xo/-o/2/x1 o 0 1",
+            "x o 0 1",
+        ]) {
+            let board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.to_fen(), fen);
+            assert_eq!(board.to_string(), fen);
+        }
+    }
+
+    #[test]
+    fn board_round_trip() {
+        // play a few moves out of every opening and check board -> fen -> board
+        for fen in openings() {
+            let mut board = Board::from_fen(fen).unwrap();
+            for ply in 0..8 {
+                let moves = generate_moves(&board);
+                if moves.is_empty() {
+                    break;
+                }
+                make_move(&mut board, &moves[(ply * 7) % moves.len()]);
+                assert_eq!(Board::from_fen(&board.to_fen()), Ok(board));
+            }
+        }
+    }
+
+    #[test]
+    fn parses_counters() {
+        let board = Board::from_fen("x5o/7/7/7/7/7/o5x o 12 34").unwrap();
+        assert_eq!(board.side_to_move, Side::White);
+        assert_eq!(board.half_move, 12);
+        assert_eq!(board.full_move, 34);
+        assert_eq!(board.boards[0], 0x1000000000040);
+        assert_eq!(board.boards[1], 0x40000000000001);
+
+        let board = Board::from_fen("x5o/7/2-1-2/7/2-1-2/7/o5x x").unwrap();
+        assert_eq!(board.half_move, 0);
+        assert_eq!(board.full_move, 1);
+        assert_eq!(board.blockers.count_ones(), 4);
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let cases = [
+            ("", FenError::Empty),
+            ("x5o/7/7/7/7/7/7/o5x x 0 1", FenError::RankCount(8)),
+            (
+                "x5o/7/2y4/7/7/7/o5x x 0 1",
+                FenError::InvalidCharacter {
+                    rank: 4,
+                    file: 2,
+                    character: 'y',
+                },
+            ),
+            (
+                "x6o/7/7/7/7/7/o5x x 0 1",
+                FenError::RankTooLong {
+                    rank: 6,
+                    expected: 7,
+                },
+            ),
+            (
+                "x5o/7/7/ooooooox/7/7/o5x x 0 1",
+                FenError::RankTooLong {
+                    rank: 3,
+                    expected: 7,
+                },
+            ),
+            (
+                "x5o/7/7/6/7/7/o5x x 0 1",
+                FenError::RankTooShort {
+                    rank: 3,
+                    files: 6,
+                    expected: 7,
+                },
+            ),
+            (
+                "x3o/5/6/5/o3x x 0 1",
+                FenError::RankTooLong {
+                    rank: 2,
+                    expected: 5,
+                },
+            ),
+            (
+                "/7/7 x 0 1",
+                FenError::RankTooShort {
+                    rank: 2,
+                    files: 0,
+                    expected: 1,
+                },
+            ),
+            ("x5o/7/7/7/7/7/o5x", FenError::MissingSideToMove),
+            (
+                "x5o/7/7/7/7/7/o5x b 0 1",
+                FenError::InvalidSideToMove("b".to_string()),
+            ),
+            (
+                "x5o/7/7/7/7/7/o5x x 256 1",
+                FenError::InvalidHalfMove("256".to_string()),
+            ),
+            (
+                "x5o/7/7/7/7/7/o5x x 0 x",
+                FenError::InvalidFullMove("x".to_string()),
+            ),
+            (
+                "x5o/7/7/7/7/7/o5x x 0 1 moves",
+                FenError::TrailingField("moves".to_string()),
+            ),
+        ];
+        for (fen, error) in cases {
+            assert_eq!(Board::from_fen(fen), Err(error), "fen: {fen}");
+        }
+    }
+
+    #[test]
+    fn parses_legal_moves() {
+        let board = Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string());
+        let mut all = generate_moves(&board);
+        all.sort();
+        let mut parsed = all
+            .iter()
+            .map(|mov| board.parse_move(&mov.to_string()).unwrap())
+            .collect::<Vec<_>>();
+        parsed.sort();
+        assert_eq!(parsed, all);
+
+        // a four character single is the same as the two character one
+        assert_eq!(board.parse_move("a7b6"), board.parse_move("b6"));
+
+        // captures are computed against the opponent after the move
+        let board = Board::new("7/7/7/2x1o2/7/7/7 x 0 1".to_string());
+        let mov = board.parse_move("d4").unwrap();
+        assert_eq!(mov.capture_square, board.other_pieces());
+
+        // forced pass
+        let board = Board::new("7/7/7/7/ooooooo/ooooooo/xxxxxxx x 0 1".to_string());
+        assert_eq!(board.parse_move("0000"), Ok(Move::PASS));
+    }
+
+    #[test]
+    fn rejects_illegal_moves() {
+        let square = |square: &str| square.parse::<Square>().unwrap();
+        let board = Board::new("x5o/7/7/7/7/2-4/o5x x 0 1".to_string());
+        let cases = [
+            ("", MoveError::InvalidFormat("".to_string())),
+            ("a1b", MoveError::InvalidFormat("a1b".to_string())),
+            ("h1", MoveError::InvalidSquare("h1".to_string())),
+            ("a0a2", MoveError::InvalidSquare("a0".to_string())),
+            ("0000", MoveError::IllegalPass),
+            ("a1a3", MoveError::NotOurStone(square("a1"))),
+            ("g1g7", MoveError::Occupied(square("g7"))),
+            ("c2", MoveError::Occupied(square("c2"))),
+            (
+                "g1d1",
+                MoveError::OutOfRange {
+                    from: square("g1"),
+                    to: square("d1"),
+                },
+            ),
+            ("d4", MoveError::NoAdjacentStone(square("d4"))),
+        ];
+        for (mov, error) in cases {
+            assert_eq!(board.parse_move(mov), Err(error), "move: {mov}");
+        }
+
+        // squares that exist on 7x7 but not on a 5x5 board
+        let board = Board::new("x3o/5/5/5/o3x x 0 1".to_string());
+        assert_eq!(
+            board.parse_move("f1"),
+            Err(MoveError::InvalidSquare("f1".to_string()))
+        );
+        assert_eq!(
+            board.parse_move("e1e6"),
+            Err(MoveError::InvalidSquare("e6".to_string()))
+        );
+        assert!(board.parse_move("e1e3").is_ok());
+
+        let board = Board::new("x5o/7/7/7/7/7/o5x x 100 1".to_string());
+        assert_eq!(board.parse_move("b2"), Err(MoveError::GameOver));
     }
 }
@@ -0,0 +1,101 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{board::BitBoard, square::Square};
+
+/// Most files or ranks a board can have, bitboards use 8 bits per rank so a spare file keeps
+/// shifted stones from wrapping onto the next rank
+pub const MAX_SIZE: u8 = 7;
+
+/// Dimensions of a rectangular board with a1 in the bottom left.
+///
+/// Every size uses the same bitboard layout as the standard 7x7 board, squares past the last
+/// file or rank are simply never part of the board.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, Hash)]
+pub struct Geometry {
+    files: u8,
+    ranks: u8,
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Geometry::STANDARD
+    }
+}
+
+impl Display for Geometry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.files, self.ranks)
+    }
+}
+
+impl Geometry {
+    /// The 7x7 board of standard Ataxx
+    pub const STANDARD: Geometry = Geometry { files: 7, ranks: 7 };
+
+    /// A board with the given number of files and ranks, `None` unless both are in `1..=MAX_SIZE`
+    pub const fn new(files: u8, ranks: u8) -> Option<Geometry> {
+        if files == 0 || ranks == 0 || files > MAX_SIZE || ranks > MAX_SIZE {
+            None
+        } else {
+            Some(Geometry { files, ranks })
+        }
+    }
+
+    pub const fn files(self) -> u8 {
+        self.files
+    }
+
+    pub const fn ranks(self) -> u8 {
+        self.ranks
+    }
+
+    /// Does the board have as many files as ranks
+    pub const fn is_square(self) -> bool {
+        self.files == self.ranks
+    }
+
+    /// Every square on the board
+    pub const fn mask(self) -> BitBoard {
+        let rank = (1 << self.files) - 1;
+        // one bit at the start of each rank, multiplying copies the first rank up the board
+        let rank_starts = 0x01010101010101 >> (8 * (MAX_SIZE - self.ranks));
+        rank * rank_starts
+    }
+
+    pub const fn contains(self, square: Square) -> bool {
+        square.file() < self.files && square.rank() < self.ranks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::square::Square;
+
+    use super::Geometry;
+
+    #[test]
+    fn masks() {
+        assert_eq!(Geometry::STANDARD.mask(), 0x7f7f7f7f7f7f7f);
+        assert_eq!(Geometry::new(5, 5).unwrap().mask(), 0x1f1f1f1f1f);
+        assert_eq!(Geometry::new(6, 4).unwrap().mask(), 0x3f3f3f3f);
+        assert_eq!(Geometry::new(1, 1).unwrap().mask(), 1);
+        assert_eq!(Geometry::new(0, 5), None);
+        assert_eq!(Geometry::new(8, 7), None);
+    }
+
+    #[test]
+    fn contains() {
+        let geometry = Geometry::new(6, 4).unwrap();
+        for (square, on_board) in [("a1", true), ("f4", true), ("g1", false), ("a5", false)] {
+            let square = square.parse::<Square>().unwrap();
+            assert_eq!(geometry.contains(square), on_board, "{square}");
+            assert_eq!(
+                geometry.mask() & square.bitboard() != 0,
+                on_board,
+                "{square}"
+            );
+        }
+    }
+}
@@ -1,4 +1,15 @@
+pub mod analysis;
 pub mod board;
+pub mod geometry;
 pub mod move_app;
 pub mod movegen;
-mod perft;
+pub mod movelist;
+pub mod perft;
+pub mod pgn;
+pub mod position;
+pub mod random;
+pub mod render;
+pub mod rules;
+pub mod square;
+pub mod symmetry;
+mod zobrist;
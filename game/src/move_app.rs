@@ -1,13 +1,15 @@
 use crate::{
     board::{Board, Side},
-    movegen::Move,
+    movegen::{Move, MoveKind},
+    zobrist,
 };
 
+/// Play `mov` on `board`, returning the old half move counter for [`unmake_move`].
+///
+/// A pass only hands the turn over, it counts towards the half move limit like any other move
+/// without a capture.
 pub fn make_move(board: &mut Board, mov: &Move) -> u8 {
-    // if mov.to == 8 {
-    //     panic!();
-    // }
-    if !mov.null {
+    if mov.kind != MoveKind::Pass {
         // add in the new stone for the side to move
         board.boards[board.side_to_move as usize] |= mov.to;
 
@@ -18,9 +20,12 @@ pub fn make_move(board: &mut Board, mov: &Move) -> u8 {
         board.boards[1 - board.side_to_move as usize] ^= mov.capture_square;
         // add the captures to our side
         board.boards[board.side_to_move as usize] |= mov.capture_square;
+
+        board.hash ^= hash_delta(board.side_to_move, mov);
     }
     // flip side to move
     board.side_to_move = !board.side_to_move;
+    board.hash ^= zobrist::SIDE_KEY;
 
     // increment full move counter iff side to move is black after flipping
     if board.side_to_move == Side::Black {
@@ -28,7 +33,7 @@ pub fn make_move(board: &mut Board, mov: &Move) -> u8 {
     }
 
     let old_half_move = board.half_move;
-    // update half move counter
+    // update half move counter, passes never capture
     if mov.capture_square != 0 {
         board.half_move = 0;
     } else {
@@ -48,8 +53,9 @@ pub fn unmake_move(board: &mut Board, mov: &Move, old_half_move: u8) {
 
     // flip the side to move
     board.side_to_move = !board.side_to_move;
+    board.hash ^= zobrist::SIDE_KEY;
 
-    if !mov.null {
+    if mov.kind != MoveKind::Pass {
         // add back the old from position (doesnt matter if the old stone is still there because of the OR operation truth table)
         board.boards[board.side_to_move as usize] |= mov.from;
 
@@ -60,5 +66,61 @@ pub fn unmake_move(board: &mut Board, mov: &Move, old_half_move: u8) {
         board.boards[1 - board.side_to_move as usize] |= mov.capture_square;
         // remove the captures from our side
         board.boards[board.side_to_move as usize] ^= mov.capture_square;
+
+        board.hash ^= hash_delta(board.side_to_move, mov);
+    }
+}
+
+/// Change in zobrist key when `side` plays `mov`, the same xor undoes it
+fn hash_delta(side: Side, mov: &Move) -> u64 {
+    // the moving side gains the to square and captures and loses the from square,
+    // the other side loses the captures
+    zobrist::stone_keys(side, mov.to | mov.from | mov.capture_square)
+        ^ zobrist::stone_keys(!side, mov.capture_square)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{Board, Side},
+        movegen::{generate_moves, Move, MoveKind},
+    };
+
+    use super::{make_move, unmake_move};
+
+    #[test]
+    fn forced_pass() {
+        // black is walled in by white and has to pass
+        let start = Board::new("7/7/7/7/ooooooo/ooooooo/xxxxxxx x 5 3".to_string());
+        assert_eq!(generate_moves(&start), [Move::PASS]);
+
+        let mut board = start;
+        let old_half_move = make_move(&mut board, &Move::PASS);
+        assert_eq!(old_half_move, 5);
+        assert_eq!(board.boards, start.boards);
+        assert_eq!(board.side_to_move, Side::White);
+        assert_eq!((board.half_move, board.full_move), (6, 3));
+        assert_eq!(board.hash, board.compute_hash());
+        assert_ne!(board.hash, start.hash);
+
+        // white has moves again, and taking the pass back restores everything
+        assert!(generate_moves(&board)
+            .iter()
+            .all(|mov| mov.kind != MoveKind::Pass));
+        unmake_move(&mut board, &Move::PASS, old_half_move);
+        assert_eq!(board, start);
+    }
+
+    #[test]
+    fn pass_by_white_ends_the_full_move() {
+        let start = Board::new("7/7/7/7/xxxxxxx/xxxxxxx/ooooooo o 0 8".to_string());
+        let mut board = start;
+        let old_half_move = make_move(&mut board, &Move::PASS);
+        assert_eq!(
+            (board.side_to_move, board.half_move, board.full_move),
+            (Side::Black, 1, 9)
+        );
+        unmake_move(&mut board, &Move::PASS, old_half_move);
+        assert_eq!(board, start);
     }
 }
@@ -2,186 +2,451 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-use crate::board::{BitBoard, Board};
+use crate::{
+    board::{BitBoard, Board},
+    movelist::MoveList,
+    square::{BitIter, Square},
+};
+
+/// What a move does with the stone it moves
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum MoveKind {
+    /// Adds a stone next to one of our stones, `from` is zero
+    Single,
+    /// Moves a stone two squares, vacating `from`
+    Double,
+    /// Passes the turn when there is no other legal move, all squares are zero
+    Pass,
+}
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
 pub struct Move {
-    pub null: bool,
+    pub kind: MoveKind,
     pub from: BitBoard,
     pub to: BitBoard,
     pub capture_square: BitBoard,
 }
 
+/// How a legal move compares to the other moves to the same square, see [`Move::tag`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum MoveTag {
+    Unique,
+    /// A double move to a square that another double reaches from a lower square, the two
+    /// positions only differ in which square was vacated
+    Equivalent,
+    /// A double move to a square a single move also reaches, the single captures the same
+    /// stones without giving up a square
+    Dominated,
+}
+
 impl Move {
-    pub fn from_str(input: &str, all: u64) -> Self {
-        if input.len() == 4 {
-            let from = an_to_bb(input[0..2].to_string());
-            let to = an_to_bb(input[2..4].to_string());
-            let capture_square =
-                (to << 1 | to >> 1 | to << 8 | to >> 8 | to << 9 | to >> 9 | to << 7 | to >> 7)
-                    & all;
-            Self {
-                null: false,
-                from,
-                to,
-                capture_square,
-            }
+    /// Tag a legal move of `board`, singles and passes are always unique.
+    ///
+    /// Captures only depend on the destination, so doubles that land where a single or a lower
+    /// double could are never better for the capture count.
+    pub fn tag(&self, board: &Board) -> MoveTag {
+        if self.kind != MoveKind::Double {
+            return MoveTag::Unique;
+        }
+        let to = Square::from_bitboard(self.to).index();
+        let our_pieces = board.current_pieces();
+        if NEIGHBOURS[to] & our_pieces != 0 {
+            MoveTag::Dominated
+        } else if DOUBLE_JUMPS[to] & our_pieces & (self.from - 1) != 0 {
+            // double jumps are symmetric, so these are the lower stones that reach `to`
+            MoveTag::Equivalent
         } else {
-            let to = an_to_bb(input[0..2].to_string());
-
-            let capture_square =
-                (to << 1 | to >> 1 | to << 8 | to >> 8 | to << 9 | to >> 9 | to << 7 | to >> 7)
-                    & all;
-            Move {
-                null: false,
-                from: 0,
-                to,
-                capture_square,
+            MoveTag::Unique
+        }
+    }
+
+    /// The pass move, played when the side to move has no other legal moves
+    pub const PASS: Move = Move {
+        kind: MoveKind::Pass,
+        from: 0,
+        to: 0,
+        capture_square: 0,
+    };
+}
+
+/// A move packed into 16 bits: from square in bits 0-5, to square in bits 6-11 and the kind in bits 12-13.
+///
+/// Captures are not stored, [`PackedMove::unpack`] recomputes them from the board the move is played on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub struct PackedMove(pub u16);
+
+impl PackedMove {
+    const SINGLE: u16 = 0;
+    const DOUBLE: u16 = 1;
+    const PASS: u16 = 2;
+
+    /// Expand into a full move, computing the captures against the side not to move on `board`
+    pub fn unpack(self, board: &Board) -> Move {
+        match self.0 >> 12 {
+            PackedMove::PASS => Move::PASS,
+            kind => {
+                let to = Square::from_index(((self.0 >> 6) & 0x3f) as u8);
+                let (kind, from) = if kind == PackedMove::DOUBLE {
+                    let from = Square::from_index((self.0 & 0x3f) as u8);
+                    (MoveKind::Double, from.bitboard())
+                } else {
+                    (MoveKind::Single, 0)
+                };
+                Move {
+                    kind,
+                    from,
+                    to: to.bitboard(),
+                    capture_square: NEIGHBOURS[to.index()] & board.other_pieces(),
+                }
             }
         }
     }
 }
 
-pub fn bb_to_an(bb: u64) -> String {
-    let alph = ["a", "b", "c", "d", "e", "f", "g"];
-    let num = [1, 2, 3, 4, 5, 6, 7];
-    let ld_zero = bb.trailing_zeros();
+impl From<Move> for PackedMove {
+    fn from(mov: Move) -> Self {
+        match mov.kind {
+            MoveKind::Pass => PackedMove(PackedMove::PASS << 12),
+            MoveKind::Single => {
+                PackedMove(PackedMove::SINGLE << 12 | (mov.to.trailing_zeros() as u16) << 6)
+            }
+            MoveKind::Double => PackedMove(
+                PackedMove::DOUBLE << 12
+                    | (mov.to.trailing_zeros() as u16) << 6
+                    | mov.from.trailing_zeros() as u16,
+            ),
+        }
+    }
+}
 
-    return format!(
-        "{}{}",
-        alph[(ld_zero % 8) as usize],
-        num[(ld_zero / 8) as usize]
-    );
+pub fn bb_to_an(bb: u64) -> String {
+    Square::from_bitboard(bb).to_string()
 }
 
 pub fn an_to_bb(an: String) -> u64 {
-    let alph = ["a", "b", "c", "d", "e", "f", "g"];
-    let num = [1, 2, 3, 4, 5, 6, 7];
-
-    return 1
-        << (alph.iter().position(|r| *r == &an[0..1]).unwrap()
-            + num
-                .iter()
-                .position(|r| *r == an[1..2].parse::<usize>().unwrap())
-                .unwrap()
-                * 8);
+    an.parse::<Square>()
+        .unwrap_or_else(|err| panic!("{err}"))
+        .bitboard()
 }
 
 impl Display for Move {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.null {
-            return write!(f, "0000");
-        }
-        if self.from == 0 {
-            write!(f, "{}", bb_to_an(self.to))
-        } else {
-            write!(f, "{}{}", bb_to_an(self.from), bb_to_an(self.to))
+        match self.kind {
+            MoveKind::Pass => write!(f, "0000"),
+            MoveKind::Single => write!(f, "{}", bb_to_an(self.to)),
+            MoveKind::Double => write!(f, "{}{}", bb_to_an(self.from), bb_to_an(self.to)),
         }
     }
 }
 
+/// Generate all legal moves into a freshly allocated vector, see [`generate_moves_into`]
 pub fn generate_moves(board: &Board) -> Vec<Move> {
+    let mut out = MoveList::new();
+    generate_moves_into(board, &mut out);
+    out.to_vec()
+}
+
+/// Generate all legal moves into `out`, replacing its contents.
+///
+/// Produces a single pass if the side to move has to pass, and no moves at all once the game is over.
+pub fn generate_moves_into(board: &Board, out: &mut MoveList) {
+    out.clear();
+    if board.game_over() {
+        return;
+    }
+    // empty squares, this also keeps moves on boards smaller than 7x7
+    let empty = board.empty();
+    push_singles(board, empty, out);
+    push_doubles(board, empty, out);
+
+    if out.is_empty() {
+        out.push(Move::PASS)
+    }
+}
+
+/// Append the single moves to `out`, these add a stone without giving up a square
+pub fn generate_singles(board: &Board, out: &mut MoveList) {
+    if !board.game_over() {
+        push_singles(board, board.empty(), out);
+    }
+}
+
+/// Append the double moves to `out`
+pub fn generate_doubles(board: &Board, out: &mut MoveList) {
+    if !board.game_over() {
+        push_doubles(board, board.empty(), out);
+    }
+}
+
+/// Append the single and double moves that capture at least one stone to `out`
+pub fn generate_captures(board: &Board, out: &mut MoveList) {
+    if !board.game_over() {
+        // a move captures iff it lands next to an enemy stone
+        let targets = board.empty() & singles(board.other_pieces());
+        push_singles(board, targets, out);
+        push_doubles(board, targets, out);
+    }
+}
+
+/// Generate the moves tagged [`MoveTag::Unique`] into `out`, replacing its contents.
+///
+/// Doubles that land where a single can are left out, and of the doubles that land on the same
+/// square only the one from the lowest square is kept.
+pub fn generate_unique_moves_into(board: &Board, out: &mut MoveList) {
+    out.clear();
+    if board.game_over() {
+        return;
+    }
+    let empty = board.empty();
+    push_singles(board, empty, out);
+
+    // squares a single reaches are dominated, and each other square is kept for the lowest from square
+    let mut seen = singles(board.current_pieces());
+    let other_pieces = board.other_pieces();
+    for from in BitIter(board.current_pieces()) {
+        let targets = DOUBLE_JUMPS[from.index()] & empty & !seen;
+        seen |= targets;
+        for to in BitIter(targets) {
+            out.push(Move {
+                kind: MoveKind::Double,
+                from: from.bitboard(),
+                to: to.bitboard(),
+                capture_square: NEIGHBOURS[to.index()] & other_pieces,
+            });
+        }
+    }
+
+    if out.is_empty() {
+        out.push(Move::PASS)
+    }
+}
+
+/// Number of moves [`generate_moves`] would produce, without generating them
+pub fn count_moves(board: &Board) -> usize {
     if board.game_over() {
-        return vec![];
+        return 0;
     }
-    let mut out = vec![];
-    let mut my_pieces = board.current_pieces();
+    let empty = board.empty();
+    let my_pieces = board.current_pieces();
+    let singles = (singles(my_pieces) & empty).count_ones();
+    let doubles = BitIter(my_pieces)
+        .map(|from| (DOUBLE_JUMPS[from.index()] & empty).count_ones())
+        .sum::<u32>();
+    // a side that cannot move has to pass
+    (singles + doubles).max(1) as usize
+}
+
+/// Push the single moves to squares in `targets`
+fn push_singles(board: &Board, targets: BitBoard, out: &mut MoveList) {
     // other persons pieces
     let other_pieces = board.other_pieces();
-    // all of the blocking pieces
-    let all_blockers = board.blockers();
 
-    // generate singles
-    //
     // this contains all of the possible single moves for that bitmask that are within the 7x7 board.
-    let singles = singles(my_pieces);
-    // find all of the single moves that move into an empty square
-    let mut legal_singles = singles & (!all_blockers);
-    // iterate through all legal single moves
-    for _ in 0..legal_singles.count_ones() {
-        // find the to mask for the single move
-        let to_mask = 1 << legal_singles.trailing_zeros();
-        // remove this from the total mask of single moves
-        legal_singles ^= to_mask;
-        // find the captures for this move
-        let captures = (to_mask << 1
-            | to_mask >> 1
-            | to_mask << 8
-            | to_mask >> 8
-            | to_mask << 9
-            | to_mask >> 9
-            | to_mask << 7
-            | to_mask >> 7)
-            & other_pieces;
-
+    let singles = singles(board.current_pieces());
+    // iterate through all single moves that land on a target
+    for to in BitIter(singles & targets) {
         out.push(Move {
-            null: false,
+            kind: MoveKind::Single,
             from: 0, // from mask doesnt matter for 1 moves since you dont remove the starting point
-            to: to_mask,
-            capture_square: captures,
+            to: to.bitboard(),
+            capture_square: NEIGHBOURS[to.index()] & other_pieces,
         });
     }
+}
 
-    // iterate through each square for the side to move
-    for _ in 0..my_pieces.count_ones() {
-        // get the from bit mask
-        let from_mask = 1 << my_pieces.trailing_zeros();
-
-        my_pieces ^= from_mask;
-        // generate 2 moves
-        //
-        // generates all of the possible double moves that are not moving oob
-        let doubles = doubles(from_mask);
-
-        // find all doubles that move into empty squares
-        let mut legal_doubles = doubles & (!all_blockers);
-        // iterate through all double moves
-        for _ in 0..legal_doubles.count_ones() {
-            // find the to mask for the double move
-            let to_mask = 1 << legal_doubles.trailing_zeros();
-            // remove this from the total mask of double moves
-            legal_doubles ^= to_mask;
-            // find the captures for this move
-            let captures = (to_mask << 1
-                | to_mask >> 1
-                | to_mask << 8
-                | to_mask >> 8
-                | to_mask << 7
-                | to_mask >> 7
-                | to_mask << 9
-                | to_mask >> 9)
-                & other_pieces;
+/// Push the double moves to squares in `targets`
+fn push_doubles(board: &Board, targets: BitBoard, out: &mut MoveList) {
+    let other_pieces = board.other_pieces();
 
+    // iterate through each square for the side to move
+    for from in BitIter(board.current_pieces()) {
+        // iterate through all doubles from this square that land on a target
+        for to in BitIter(DOUBLE_JUMPS[from.index()] & targets) {
             out.push(Move {
-                null: false,
-                from: from_mask,
-                to: to_mask,
-                capture_square: captures,
+                kind: MoveKind::Double,
+                from: from.bitboard(),
+                to: to.bitboard(),
+                capture_square: NEIGHBOURS[to.index()] & other_pieces,
             });
         }
     }
+}
 
-    if out.is_empty() {
-        out.push(Move {
-            null: true,
-            from: 0,
-            to: 0,
-            capture_square: 0,
-        })
+/// Squares one step away from each square, the squares a stone captures when it lands there
+pub const NEIGHBOURS: [BitBoard; 64] = {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        table[square] = singles(1 << square);
+        square += 1;
     }
+    table
+};
 
-    out
-}
+/// Squares exactly two steps away from each square, the destinations of double moves from it
+pub const DOUBLE_JUMPS: [BitBoard; 64] = {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        table[square] = doubles(1 << square);
+        square += 1;
+    }
+    table
+};
 
-pub fn singles(bb: u64) -> u64 {
+pub const fn singles(bb: u64) -> u64 {
     (bb << 1 | bb >> 1 | bb << 8 | bb >> 8 | bb << 9 | bb >> 9 | bb << 7 | bb >> 7)
         & 0x7f7f7f7f7f7f7f_u64
 }
 
-pub fn doubles(bb: u64) -> u64 {
+pub const fn doubles(bb: u64) -> u64 {
     ((bb << 2 | bb << 10 | bb << 18 | bb >> 6 | bb >> 14 | bb << 17 | bb >> 15) & 0x7e7e7e7e7e7e7e) |
         // center
         ((bb << 16 | bb >> 16) & 0x7f7f7f7f7f7f7f) |
         // left
         ((bb >> 2 | bb >> 10 | bb >> 18 | bb << 6 | bb << 14 | bb << 15 | bb >> 17) & 0x3f3f3f3f3f3f3f)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::Board,
+        move_app::{make_move, unmake_move},
+    };
+
+    use crate::{
+        geometry::Geometry,
+        movelist::MoveList,
+        random::{random_position, Rng},
+    };
+
+    use super::{
+        count_moves, generate_captures, generate_doubles, generate_moves, generate_singles,
+        generate_unique_moves_into, Move, MoveKind, MoveTag, PackedMove,
+    };
+
+    fn check_packing(board: &mut Board, depth: u8) {
+        if depth == 0 {
+            return;
+        }
+        for mov in generate_moves(board) {
+            assert_eq!(PackedMove::from(mov).unpack(board), mov, "fen: {board}");
+            let delta = make_move(board, &mov);
+            check_packing(board, depth - 1);
+            unmake_move(board, &mov, delta);
+        }
+    }
+
+    #[test]
+    fn packed_round_trip() {
+        for fen in [
+            "x5o/7/7/7/7/7/o5x x 0 1",
+            "x5o/7/2-1-2/3-3/2-1-2/7/o5x o 0 1",
+            "7/7/7/2x1o2/7/7/7 x 0 1",
+            "7/7/7/7/ooooooo/ooooooo/xxxxxxx x 0 1",
+        ] {
+            check_packing(&mut Board::new(fen.to_string()), 3);
+        }
+    }
+
+    #[test]
+    fn staged_generators_agree() {
+        let mut rng = Rng::new(17);
+        let mut boards = vec![
+            Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string()),
+            Board::new("7/7/7/7/ooooooo/ooooooo/xxxxxxx x 0 1".to_string()),
+            Board::new("7/7/7/7/-------/-------/x5o x 0 1".to_string()),
+            Board::new("x5o/7/7/7/7/7/o5x x 100 1".to_string()),
+        ];
+        for plies in 0..40 {
+            boards.push(random_position(&mut rng, Geometry::STANDARD, plies, 0.1).unwrap());
+            let small = Geometry::new(5, 5).unwrap();
+            boards.push(random_position(&mut rng, small, plies / 2, 0.1).unwrap());
+        }
+        for board in boards {
+            let all = generate_moves(&board);
+            let sorted = |mut moves: Vec<Move>| {
+                moves.sort();
+                moves
+            };
+            let mut list = MoveList::new();
+            generate_singles(&board, &mut list);
+            assert!(
+                list.iter().all(|mov| mov.kind == MoveKind::Single),
+                "fen: {board}"
+            );
+            generate_doubles(&board, &mut list);
+            let expected = all
+                .iter()
+                .filter(|mov| mov.kind != MoveKind::Pass)
+                .copied()
+                .collect();
+            assert_eq!(sorted(list.to_vec()), sorted(expected), "fen: {board}");
+
+            list.clear();
+            generate_captures(&board, &mut list);
+            let expected = all
+                .iter()
+                .filter(|mov| mov.capture_square != 0)
+                .copied()
+                .collect();
+            assert_eq!(sorted(list.to_vec()), sorted(expected), "fen: {board}");
+
+            assert_eq!(count_moves(&board), all.len(), "fen: {board}");
+
+            list.clear();
+            generate_unique_moves_into(&board, &mut list);
+            let expected = all
+                .iter()
+                .filter(|mov| mov.tag(&board) == MoveTag::Unique)
+                .copied()
+                .collect();
+            assert_eq!(sorted(list.to_vec()), sorted(expected), "fen: {board}");
+        }
+    }
+
+    #[test]
+    fn notation_round_trip() {
+        for fen in [
+            "x5o/7/2-1-2/3-3/2-1-2/7/o5x o 0 1",
+            "7/7/7/2x1o2/7/7/7 x 0 1",
+            "7/7/7/7/ooooooo/ooooooo/xxxxxxx x 0 1",
+        ] {
+            let board = Board::new(fen.to_string());
+            for mov in generate_moves(&board) {
+                let notation = mov.to_string();
+                let expected_len = match mov.kind {
+                    MoveKind::Single => 2,
+                    MoveKind::Double | MoveKind::Pass => 4,
+                };
+                assert_eq!(notation.len(), expected_len);
+                assert_eq!(board.parse_move(&notation), Ok(mov));
+            }
+        }
+        assert_eq!(Move::PASS.to_string(), "0000");
+
+        // a single written with its from square reads back as the single
+        let board = Board::new("7/7/7/7/7/7/x5o x 0 1".to_string());
+        let single = board.parse_move("a1b2").unwrap();
+        assert_eq!(single.kind, MoveKind::Single);
+        assert_eq!(single.to_string(), "b2");
+        assert_eq!(board.parse_move("b2"), Ok(single));
+    }
+
+    #[test]
+    fn tags() {
+        // black on a1 and c1, white on b4
+        let board = Board::new("7/7/7/1o5/7/7/x1x4 x 0 1".to_string());
+        let tag = |mov: &str| board.parse_move(mov).unwrap().tag(&board);
+        assert_eq!(tag("b2"), MoveTag::Unique);
+        assert_eq!(tag("a1a3"), MoveTag::Unique);
+        assert_eq!(tag("c1a3"), MoveTag::Equivalent);
+        assert_eq!(tag("c1e3"), MoveTag::Unique);
+        assert_eq!(tag("a1c3"), MoveTag::Unique);
+        assert_eq!(tag("c1c3"), MoveTag::Equivalent);
+        assert_eq!(tag("c1b3"), MoveTag::Equivalent);
+        assert_eq!(tag("a1b3"), MoveTag::Unique);
+        assert_eq!(tag("a1c2"), MoveTag::Dominated);
+        assert_eq!(Move::PASS.tag(&board), MoveTag::Unique);
+    }
+}
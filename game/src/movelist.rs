@@ -0,0 +1,69 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::movegen::Move;
+
+/// Upper bound on the number of legal moves in any position.
+///
+/// The densest 7x7 positions found by a local search have just under 200 moves.
+pub const MAX_MOVES: usize = 256;
+
+/// Fixed capacity, stack allocated list of moves
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        MoveList {
+            moves: [Move::PASS; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    /// Add a move to the end of the list, panics if the list is full
+    pub fn push(&mut self, mov: Move) {
+        self.moves[self.len] = mov;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &Self::Target {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl std::fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
@@ -1,36 +1,262 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    thread,
+};
+
 use crate::{
     board::Board,
     move_app::{make_move, unmake_move},
-    movegen::generate_moves,
+    movegen::{generate_moves_into, Move, MoveTag},
+    movelist::MoveList,
+    rules,
 };
 
-// clippy complains about this for some godawful reason
-#[allow(dead_code)]
-fn perft(board: &mut Board, depth: u8, max_depth: u8) -> u64 {
+/// Count the leaf nodes of the move tree `depth` plies deep, visiting every leaf
+pub fn perft(board: &mut Board, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
     let mut nodes = 0;
+    let mut moves = MoveList::new();
+    generate_moves_into(board, &mut moves);
+    for mov in &moves {
+        let before = *board;
+        let delta = make_move(board, mov);
+        debug_assert_eq!(board.hash, board.compute_hash());
+        nodes += perft(board, depth - 1);
+        unmake_move(board, mov, delta);
+        debug_assert_eq!(*board, before);
+    }
+
+    nodes
+}
+
+/// How many of the moves in a tree are tagged equivalent or dominated, see [`Move::tag`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TagStats {
+    /// Positions whose moves were counted
+    pub nodes: u64,
+    pub moves: u64,
+    pub equivalent: u64,
+    pub dominated: u64,
+}
+
+impl TagStats {
+    /// Average number of moves per position
+    pub fn branching_factor(&self) -> f64 {
+        self.moves as f64 / self.nodes.max(1) as f64
+    }
+
+    /// Average number of moves per position without the equivalent and dominated ones
+    pub fn unique_branching_factor(&self) -> f64 {
+        (self.moves - self.equivalent - self.dominated) as f64 / self.nodes.max(1) as f64
+    }
+}
+
+impl Display for TagStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent = |count: u64| 100.0 * count as f64 / self.moves.max(1) as f64;
+        write!(
+            f,
+            "nodes {}, moves {}, equivalent {} ({:.1}%), dominated {} ({:.1}%), branching factor {:.2} -> {:.2}",
+            self.nodes,
+            self.moves,
+            self.equivalent,
+            percent(self.equivalent),
+            self.dominated,
+            percent(self.dominated),
+            self.branching_factor(),
+            self.unique_branching_factor(),
+        )
+    }
+}
+
+/// Tag the moves of every position less than `depth` plies below `board`, walking the full tree
+pub fn tag_stats(board: &mut Board, depth: u8) -> TagStats {
+    let mut stats = TagStats::default();
+    collect_tag_stats(board, depth, &mut stats);
+    stats
+}
+
+fn collect_tag_stats(board: &mut Board, depth: u8, stats: &mut TagStats) {
+    if depth == 0 {
+        return;
+    }
+    let mut moves = MoveList::new();
+    generate_moves_into(board, &mut moves);
+    stats.nodes += 1;
+    stats.moves += moves.len() as u64;
+    for mov in &moves {
+        match mov.tag(board) {
+            MoveTag::Unique => {}
+            MoveTag::Equivalent => stats.equivalent += 1,
+            MoveTag::Dominated => stats.dominated += 1,
+        }
+        let delta = make_move(board, mov);
+        collect_tag_stats(board, depth - 1, stats);
+        unmake_move(board, mov, delta);
+    }
+}
+
+/// Same count as [`perft`], but the last ply is counted from the length of the move list
+pub fn perft_bulk(board: &mut Board, depth: u8) -> u64 {
     if depth == 0 {
         return 1;
     }
-    let mut counter = 0;
-    for mov in &generate_moves(board) {
-        let mut subtree_nodes = 0;
-        let t1 = board.clone();
+    let mut moves = MoveList::new();
+    generate_moves_into(board, &mut moves);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    let mut nodes = 0;
+    for mov in &moves {
         let delta = make_move(board, mov);
-        let inc = perft(board, depth - 1, max_depth);
-        subtree_nodes += inc;
-        nodes += inc;
+        nodes += perft_bulk(board, depth - 1);
         unmake_move(board, mov, delta);
-        assert_eq!(board.clone(), t1);
-        if depth == max_depth {
-            counter += 1;
-            println!("{}) {} {}", counter, mov, subtree_nodes);
-            // println!("{:?}", mov);
+    }
+
+    nodes
+}
+
+/// Bulk counted perft split by root move, in move generation order
+pub fn perft_divide(board: &mut Board, depth: u8) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return vec![];
+    }
+    let mut moves = MoveList::new();
+    generate_moves_into(board, &mut moves);
+    moves
+        .iter()
+        .map(|mov| {
+            let delta = make_move(board, mov);
+            let nodes = perft_bulk(board, depth - 1);
+            unmake_move(board, mov, delta);
+            (*mov, nodes)
+        })
+        .collect()
+}
+
+/// A single perft table slot, the nodes below `hash` at `depth`
+#[derive(Clone, Copy, Default)]
+struct PerftEntry {
+    hash: u64,
+    depth: u8,
+    nodes: u64,
+}
+
+/// Always replace hash table of subtree node counts
+pub struct PerftTable {
+    entries: Vec<PerftEntry>,
+}
+
+impl PerftTable {
+    /// Table using roughly `mb` megabytes
+    pub fn new(mb: usize) -> PerftTable {
+        let size = (mb * 1024 * 1024 / std::mem::size_of::<PerftEntry>()).max(1);
+        PerftTable {
+            entries: vec![PerftEntry::default(); size],
+        }
+    }
+
+    fn index(&self, hash: u64) -> usize {
+        (hash % self.entries.len() as u64) as usize
+    }
+}
+
+/// Bulk counted perft that caches subtree counts in `table`
+pub fn perft_hashed(board: &mut Board, depth: u8, table: &mut PerftTable) -> u64 {
+    if depth <= 1 {
+        return perft_bulk(board, depth);
+    }
+    // the key doesnt include the half move clock, so only share subtrees that cant reach the 50 move rule
+    let cacheable = (board.half_move as u16 + depth as u16) < rules::HALF_MOVE_LIMIT as u16;
+    let index = table.index(board.hash);
+    if cacheable {
+        let entry = table.entries[index];
+        // depth 0 entries are never stored, so an empty slot never matches
+        if entry.hash == board.hash && entry.depth == depth {
+            return entry.nodes;
         }
     }
 
+    let mut moves = MoveList::new();
+    generate_moves_into(board, &mut moves);
+    let mut nodes = 0;
+    for mov in &moves {
+        let delta = make_move(board, mov);
+        nodes += perft_hashed(board, depth - 1, table);
+        unmake_move(board, mov, delta);
+    }
+
+    if cacheable {
+        table.entries[index] = PerftEntry {
+            hash: board.hash,
+            depth,
+            nodes,
+        };
+    }
     nodes
 }
 
+/// Bulk counted perft with the root moves shared out between `threads` worker threads.
+///
+/// Each worker gets its own perft table of `hash_mb` megabytes, or none if `hash_mb` is 0.
+pub fn perft_parallel(board: &Board, depth: u8, threads: usize, hash_mb: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    perft_parallel_divide(board, depth, threads, hash_mb)
+        .iter()
+        .map(|(_, nodes)| nodes)
+        .sum()
+}
+
+/// [`perft_divide`] with the root moves shared out between worker threads, see [`perft_parallel`]
+pub fn perft_parallel_divide(
+    board: &Board,
+    depth: u8,
+    threads: usize,
+    hash_mb: usize,
+) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return vec![];
+    }
+    let mut moves = MoveList::new();
+    generate_moves_into(board, &mut moves);
+    let counts = moves.iter().map(|_| AtomicU64::new(0)).collect::<Vec<_>>();
+    // workers pull the next unsearched root move from this counter
+    let next = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                let mut board = *board;
+                let mut table = (hash_mb > 0).then(|| PerftTable::new(hash_mb));
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(mov) = moves.get(index) else {
+                        break;
+                    };
+                    let delta = make_move(&mut board, mov);
+                    let nodes = match &mut table {
+                        Some(table) => perft_hashed(&mut board, depth - 1, table),
+                        None => perft_bulk(&mut board, depth - 1),
+                    };
+                    unmake_move(&mut board, mov, delta);
+                    counts[index].store(nodes, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+
+    moves
+        .iter()
+        .zip(counts)
+        .map(|(mov, nodes)| (*mov, nodes.into_inner()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -40,7 +266,9 @@ mod tests {
         movegen::{bb_to_an, generate_moves},
     };
 
-    use super::perft;
+    use super::{
+        perft, perft_bulk, perft_divide, perft_hashed, perft_parallel, tag_stats, PerftTable,
+    };
 
     #[test]
     fn it_works() {
@@ -58,7 +286,44 @@ mod tests {
     }
     #[test]
     fn perft_all() {
-        let tests = vec![
+        for (idx, (fen, numbers)) in perft_vectors().iter().enumerate() {
+            println!("fen: {}, idx : {}", fen, idx);
+            perft_test(numbers.to_vec(), fen.to_string(), idx);
+        }
+    }
+
+    #[test]
+    fn parallel_and_hashed() {
+        for (fen, numbers) in perft_vectors() {
+            let board = Board::new(fen.to_string());
+            let mut table = PerftTable::new(1);
+            for (depth, number) in numbers.iter().enumerate() {
+                let depth = depth as u8;
+                assert_eq!(perft_parallel(&board, depth, 3, 0), *number, "fen: {fen}");
+                assert_eq!(perft_parallel(&board, depth, 2, 1), *number, "fen: {fen}");
+                assert_eq!(
+                    perft_hashed(&mut board.clone(), depth, &mut table),
+                    *number,
+                    "fen: {fen}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hashed_near_half_move_limit() {
+        // subtrees that reach the 50 move rule are not shared between different clocks
+        let mut table = PerftTable::new(1);
+        for half_move in [96, 97, 98, 99, 0] {
+            let fen = format!("x5o/7/7/7/7/7/o5x x {half_move} 1");
+            let mut board = Board::new(fen);
+            let expected = perft_bulk(&mut board, 4);
+            assert_eq!(perft_hashed(&mut board, 4, &mut table), expected);
+        }
+    }
+
+    fn perft_vectors() -> Vec<(&'static str, Vec<u64>)> {
+        vec![
             (
                 "x5o/7/7/7/7/7/o5x x 0 1",
                 vec![1, 16, 256, 6460, 155888, 4752668],
@@ -127,14 +392,74 @@ mod tests {
                 "7/7/7/7/-------/-------/x5o o 0 1",
                 vec![1, 2, 4, 13, 30, 73, 174],
             ),
-        ];
+            (
+                "x3o/5/5/5/o3x x 0 1",
+                vec![1, 16, 244, 4592, 86956, 1790556],
+            ),
+            (
+                "x4o/6/6/6/6/o4x x 0 1",
+                vec![1, 16, 256, 5884, 131140, 3487848],
+            ),
+        ]
+    }
 
-        for (idx, (fen, numbers)) in tests.iter().enumerate() {
-            println!("fen: {}, idx : {}", fen, idx);
-            perft_test(numbers.to_vec(), fen.to_string(), idx);
+    #[test]
+    fn smaller_boards() {
+        // a smaller board plays exactly like a 7x7 board with the missing squares filled by gaps
+        let cases = [
+            (
+                "x3o/5/5/5/o3x x 0 1",
+                "-------/-x3o-/-5-/-5-/-5-/-o3x-/------- x 0 1",
+            ),
+            (
+                "x4o/6/6/6/6/o4x x 0 1",
+                "-------/x4o-/6-/6-/6-/6-/o4x- x 0 1",
+            ),
+            (
+                "x4o/1-4/3x1o/o4x o 0 1",
+                "-------/-------/-------/x4o-/1-4-/3x1o-/o4x- o 0 1",
+            ),
+            (
+                "x1o/3/o1x x 0 1",
+                "-------/-------/-------/-------/x1o----/3----/o1x---- x 0 1",
+            ),
+        ];
+        for (small, embedded) in cases {
+            let mut small = Board::new(small.to_string());
+            let mut embedded = Board::new(embedded.to_string());
+            for depth in 0..5 {
+                assert_eq!(
+                    perft(&mut small, depth),
+                    perft(&mut embedded, depth),
+                    "{small} at depth {depth}"
+                );
+            }
         }
     }
 
+    #[test]
+    fn divide_sums_to_total() {
+        let mut board = Board::new("x5o/7/2-1-2/3-3/2-1-2/7/o5x x 0 1".to_string());
+        let divide = perft_divide(&mut board, 3);
+        assert_eq!(divide.len(), 14);
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 4100);
+        assert!(perft_divide(&mut board, 0).is_empty());
+    }
+
+    #[test]
+    fn tag_stats_cover_tree() {
+        let mut board = Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string());
+        let stats = tag_stats(&mut board, 1);
+        assert_eq!((stats.nodes, stats.moves), (1, 16));
+        assert_eq!((stats.equivalent, stats.dominated), (0, 0));
+
+        let stats = tag_stats(&mut board, 3);
+        assert_eq!(stats.nodes, 1 + 16 + 256);
+        assert_eq!(stats.moves, 16 + 256 + 6460);
+        assert!(stats.equivalent > 0 && stats.dominated > 0);
+        assert!(stats.unique_branching_factor() < stats.branching_factor());
+    }
+
     #[test]
     fn game_end() {
         let white_win = "6o/7/2o4/3o3/4o2/7/o6 o 0 1";
@@ -158,15 +483,16 @@ mod tests {
                 let moves = generate_moves(&board);
                 make_move(&mut board, &moves[0]);
                 // let moves = generate_moves(&board);
-                println!("{}", board.boards[0]);
+                println!("{}", board);
 
                 println!("Count: {}", moves.len());
                 println!("Moves: {:?}", moves);
-                let nodes = perft(&mut board, 2 as u8, 2 as u8);
+                let nodes = perft(&mut board, 2);
                 assert_eq!(*number, nodes);
             } else {
-                let nodes = perft(&mut board, depth as u8, depth as u8);
+                let nodes = perft(&mut board, depth as u8);
                 assert_eq!(*number, nodes);
+                assert_eq!(*number, perft_bulk(&mut board, depth as u8));
             }
         }
     }
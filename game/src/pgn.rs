@@ -0,0 +1,584 @@
+//! Games in a PGN style text format.
+//!
+//! ```text
+//! [Event "datagen"]
+//! [Black "sea_baxx"]
+//! [White "sea_baxx"]
+//! [Result "1-0"]
+//! [FEN "x5o/7/7/7/7/7/o5x x 0 1"]
+//!
+//! 1. f2 {31} a1c3 {-12} 2. g3 {40} 1-0
+//! ```
+//!
+//! Black moves first, so `1-0` is a win for black and `0-1` a win for white. Moves use the same
+//! notation as UAI, and a comment after a move holds the score the engine gave it, from the
+//! point of view of the side that played it.
+
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use crate::{
+    board::{Board, FenError, MoveError, Side, Status},
+    move_app::make_move,
+    movegen::Move,
+};
+
+/// Start position used when a game has no `FEN` tag
+const START_FEN: &str = "x5o/7/7/7/7/7/o5x x 0 1";
+
+/// Moves are wrapped onto a new line before they pass this column
+const LINE_WIDTH: usize = 80;
+
+/// Outcome stored in the `Result` tag and at the end of the move list
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    BlackWins,
+    WhiteWins,
+    Draw,
+    /// The game is still going on or the result is not known
+    Unfinished,
+}
+
+impl GameResult {
+    /// The result of a game given the status of its final position and the side to move there
+    pub fn from_status(status: Status, side_to_move: Side) -> GameResult {
+        match (status, side_to_move) {
+            (Status::Winner, Side::Black) | (Status::Loser, Side::White) => GameResult::BlackWins,
+            (Status::Winner, Side::White) | (Status::Loser, Side::Black) => GameResult::WhiteWins,
+            (Status::Draw, _) => GameResult::Draw,
+            (Status::Ongoing, _) => GameResult::Unfinished,
+        }
+    }
+
+    /// The status of the final position for `side_to_move`, the inverse of [`GameResult::from_status`]
+    pub fn status(self, side_to_move: Side) -> Status {
+        match (self, side_to_move) {
+            (GameResult::BlackWins, Side::Black) | (GameResult::WhiteWins, Side::White) => {
+                Status::Winner
+            }
+            (GameResult::BlackWins, Side::White) | (GameResult::WhiteWins, Side::Black) => {
+                Status::Loser
+            }
+            (GameResult::Draw, _) => Status::Draw,
+            (GameResult::Unfinished, _) => Status::Ongoing,
+        }
+    }
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self {
+            GameResult::BlackWins => "1-0",
+            GameResult::WhiteWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unfinished => "*",
+        };
+        write!(f, "{result}")
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = PgnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1-0" => Ok(GameResult::BlackWins),
+            "0-1" => Ok(GameResult::WhiteWins),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*" => Ok(GameResult::Unfinished),
+            _ => Err(PgnError::InvalidResult(s.to_string())),
+        }
+    }
+}
+
+/// Reasons a game record can fail to parse
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PgnError {
+    /// A tag pair is not of the form `[Name "value"]`
+    InvalidTag(String),
+    /// The `Result` tag holds something other than `1-0`, `0-1`, `1/2-1/2` or `*`
+    InvalidResult(String),
+    /// The `FEN` tag does not hold a valid position
+    Fen(FenError),
+    /// A move is not legal in the position it is played from, `ply` counts from 1
+    Move {
+        ply: usize,
+        mov: String,
+        err: MoveError,
+    },
+    /// A `{` comment is never closed
+    UnterminatedComment,
+    /// A `[` tag is never closed
+    UnterminatedTag,
+    /// The input does not contain a game
+    NoGame,
+}
+
+impl From<FenError> for PgnError {
+    fn from(err: FenError) -> Self {
+        PgnError::Fen(err)
+    }
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::InvalidTag(tag) => write!(f, "invalid tag '{tag}'"),
+            PgnError::InvalidResult(result) => write!(f, "invalid result '{result}'"),
+            PgnError::Fen(err) => write!(f, "invalid fen: {err}"),
+            PgnError::Move { ply, mov, err } => write!(f, "invalid move {mov} at ply {ply}: {err}"),
+            PgnError::UnterminatedComment => write!(f, "unterminated comment"),
+            PgnError::UnterminatedTag => write!(f, "unterminated tag"),
+            PgnError::NoGame => write!(f, "no game found"),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+/// A move of a game record and the comment that follows it
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RecordedMove {
+    pub mov: Move,
+    pub comment: Option<String>,
+}
+
+impl RecordedMove {
+    /// The score in the comment, if the comment is a single number
+    pub fn score(&self) -> Option<i32> {
+        self.comment.as_ref()?.trim().parse().ok()
+    }
+}
+
+/// A game with its players, result, starting position and moves
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct GameRecord {
+    pub event: String,
+    pub black: String,
+    pub white: String,
+    pub result: GameResult,
+    pub start: Board,
+    pub moves: Vec<RecordedMove>,
+}
+
+impl GameRecord {
+    /// An unfinished game from `start` with unknown event and players
+    pub fn new(start: Board) -> GameRecord {
+        GameRecord {
+            event: "?".to_string(),
+            black: "?".to_string(),
+            white: "?".to_string(),
+            result: GameResult::Unfinished,
+            start,
+            moves: vec![],
+        }
+    }
+
+    /// The position after all of the moves
+    pub fn final_board(&self) -> Board {
+        let mut board = self.start;
+        for recorded in &self.moves {
+            make_move(&mut board, &recorded.mov);
+        }
+        board
+    }
+}
+
+impl Display for GameRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tag = |name: &str, value: &str| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("[{name} \"{value}\"]")
+        };
+        writeln!(f, "{}", tag("Event", &self.event))?;
+        writeln!(f, "{}", tag("Black", &self.black))?;
+        writeln!(f, "{}", tag("White", &self.white))?;
+        writeln!(f, "{}", tag("Result", &self.result.to_string()))?;
+        writeln!(f, "{}", tag("FEN", &self.start.to_fen()))?;
+        writeln!(f)?;
+
+        let mut tokens = vec![];
+        let mut side = self.start.side_to_move;
+        let mut full_move = self.start.full_move;
+        for (ply, recorded) in self.moves.iter().enumerate() {
+            match side {
+                Side::Black => tokens.push(format!("{full_move}.")),
+                Side::White if ply == 0 => tokens.push(format!("{full_move}...")),
+                Side::White => {}
+            }
+            tokens.push(recorded.mov.to_string());
+            if let Some(comment) = &recorded.comment {
+                // a closing brace would end the comment early
+                tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+            }
+            if side == Side::White {
+                full_move += 1;
+            }
+            side = !side;
+        }
+        tokens.push(self.result.to_string());
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length != 0 && line_length + 1 + token.len() > LINE_WIDTH {
+                writeln!(f)?;
+                line_length = 0;
+            }
+            if line_length != 0 {
+                write!(f, " ")?;
+                line_length += 1;
+            }
+            write!(f, "{token}")?;
+            line_length += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = PgnError;
+
+    /// Parse the first game in `s`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        read_games(s)?.into_iter().next().ok_or(PgnError::NoGame)
+    }
+}
+
+/// Parse every game in `input`, games are separated by their tag pairs or by a result
+pub fn read_games(input: &str) -> Result<Vec<GameRecord>, PgnError> {
+    let mut games = vec![];
+    let mut builder = GameBuilder::default();
+    for token in tokens(input) {
+        match token? {
+            Token::Tag(name, value) => {
+                if builder.in_moves {
+                    games.push(builder.finish()?);
+                    builder = GameBuilder::default();
+                }
+                builder.tag(&name, value)?;
+            }
+            Token::Comment(comment) => {
+                if let Some(last) = builder.moves.last_mut() {
+                    last.comment = Some(comment);
+                }
+            }
+            Token::Word(word) => {
+                if let Ok(result) = word.parse::<GameResult>() {
+                    builder.result = Some(result);
+                    games.push(builder.finish()?);
+                    builder = GameBuilder::default();
+                } else if !is_move_number(&word) {
+                    builder.play(&word)?;
+                }
+            }
+        }
+    }
+    if builder.in_moves || builder.has_tags {
+        games.push(builder.finish()?);
+    }
+    Ok(games)
+}
+
+/// `12.` or `12...`
+fn is_move_number(word: &str) -> bool {
+    let digits = word.trim_end_matches('.');
+    digits.len() < word.len() && !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// A game being read, the board is set up by the first move since the `FEN` tag comes before it
+#[derive(Default)]
+struct GameBuilder {
+    event: Option<String>,
+    black: Option<String>,
+    white: Option<String>,
+    /// Result from the termination marker, or failing that the `Result` tag
+    result: Option<GameResult>,
+    start: Option<Board>,
+    board: Option<Board>,
+    moves: Vec<RecordedMove>,
+    has_tags: bool,
+    in_moves: bool,
+}
+
+impl GameBuilder {
+    fn tag(&mut self, name: &str, value: String) -> Result<(), PgnError> {
+        self.has_tags = true;
+        match name {
+            "Event" => self.event = Some(value),
+            "Black" => self.black = Some(value),
+            "White" => self.white = Some(value),
+            "Result" => self.result = Some(value.parse()?),
+            "FEN" => self.start = Some(Board::from_fen(&value)?),
+            // other tags are allowed but not kept
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn board(&mut self) -> &mut Board {
+        let start = *self
+            .start
+            .get_or_insert_with(|| Board::new(START_FEN.to_string()));
+        self.board.get_or_insert(start)
+    }
+
+    fn play(&mut self, word: &str) -> Result<(), PgnError> {
+        self.in_moves = true;
+        let ply = self.moves.len() + 1;
+        let board = self.board();
+        let mov = board.parse_move(word).map_err(|err| PgnError::Move {
+            ply,
+            mov: word.to_string(),
+            err,
+        })?;
+        make_move(board, &mov);
+        self.moves.push(RecordedMove { mov, comment: None });
+        Ok(())
+    }
+
+    fn finish(self) -> Result<GameRecord, PgnError> {
+        let start = self
+            .start
+            .unwrap_or_else(|| Board::new(START_FEN.to_string()));
+        let unknown = || "?".to_string();
+        Ok(GameRecord {
+            event: self.event.unwrap_or_else(unknown),
+            black: self.black.unwrap_or_else(unknown),
+            white: self.white.unwrap_or_else(unknown),
+            result: self.result.unwrap_or(GameResult::Unfinished),
+            start,
+            moves: self.moves,
+        })
+    }
+}
+
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Word(String),
+}
+
+/// Split the input into tag pairs, comments and whitespace separated words
+fn tokens(input: &str) -> impl Iterator<Item = Result<Token, PgnError>> + '_ {
+    let mut rest = input;
+    std::iter::from_fn(move || {
+        rest = rest.trim_start();
+        let token = match rest.chars().next()? {
+            '[' => match tag_end(rest) {
+                Some(end) => {
+                    let tag = &rest[1..end];
+                    rest = &rest[end + 1..];
+                    parse_tag(tag)
+                }
+                None => {
+                    rest = "";
+                    Err(PgnError::UnterminatedTag)
+                }
+            },
+            '{' => match rest.find('}') {
+                Some(end) => {
+                    let comment = rest[1..end].trim().to_string();
+                    rest = &rest[end + 1..];
+                    Ok(Token::Comment(comment))
+                }
+                None => {
+                    rest = "";
+                    Err(PgnError::UnterminatedComment)
+                }
+            },
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || c == '{' || c == '[')
+                    .unwrap_or(rest.len());
+                let word = rest[..end].to_string();
+                rest = &rest[end..];
+                Ok(Token::Word(word))
+            }
+        };
+        Some(token)
+    })
+}
+
+/// Index of the `]` that closes the tag at the start of `input`, skipping any inside the quoted
+/// value, where `\"` is an escaped quote
+fn tag_end(input: &str) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, character) in input.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ']' if !quoted => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Split `Name "value"` and undo the escaping of quotes and backslashes in the value
+fn parse_tag(tag: &str) -> Result<Token, PgnError> {
+    let invalid = || PgnError::InvalidTag(tag.to_string());
+    let (name, value) = tag
+        .trim()
+        .split_once(char::is_whitespace)
+        .ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' => unescaped.push(chars.next().ok_or_else(invalid)?),
+            character => unescaped.push(character),
+        }
+    }
+    Ok(Token::Tag(name.to_string(), unescaped))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{Board, MoveError, Side, Status},
+        move_app::make_move,
+        movegen::generate_moves,
+        random::Rng,
+    };
+
+    use super::{read_games, GameRecord, GameResult, PgnError, RecordedMove};
+
+    const SAMPLE: &str = r#"[Event "test \"match\""]
+[Site "nowhere"]
+[Black "one"]
+[White "two"]
+[Result "0-1"]
+[FEN "x5o/7/7/7/7/7/o5x o 0 1"]
+
+1... a2 {15} 2. f1 { -3 } b3 {comment} 3. f1e3 0-1
+
+[Event "second"]
+1. b6 a2 *
+"#;
+
+    #[test]
+    fn reads_tags_moves_and_comments() {
+        let games = read_games(SAMPLE).unwrap();
+        assert_eq!(games.len(), 2);
+
+        let game = &games[0];
+        assert_eq!(game.event, "test \"match\"");
+        assert_eq!(game.black, "one");
+        assert_eq!(game.white, "two");
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.start.side_to_move, Side::White);
+        let moves = game
+            .moves
+            .iter()
+            .map(|recorded| recorded.mov.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(moves, ["a2", "f1", "b3", "f1e3"]);
+        let scores = game
+            .moves
+            .iter()
+            .map(RecordedMove::score)
+            .collect::<Vec<_>>();
+        assert_eq!(scores, [Some(15), Some(-3), None, None]);
+        assert_eq!(game.final_board().full_move, 3);
+
+        let game = &games[1];
+        assert_eq!(game.event, "second");
+        assert_eq!(game.white, "?");
+        assert_eq!(game.result, GameResult::Unfinished);
+        assert_eq!(
+            game.start,
+            Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string())
+        );
+        assert_eq!(game.moves.len(), 2);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = Rng::new(3);
+        for plies in [0, 1, 7, 60] {
+            let start = Board::new("x5o/7/2-1-2/7/2-1-2/7/o5x o 4 9".to_string());
+            let mut record = GameRecord::new(start);
+            record.event = "round \\ trip".to_string();
+            let mut board = start;
+            for ply in 0..plies {
+                let moves = generate_moves(&board);
+                if moves.is_empty() {
+                    break;
+                }
+                let mov = moves[rng.below(moves.len() as u64) as usize];
+                make_move(&mut board, &mov);
+                record.moves.push(RecordedMove {
+                    mov,
+                    comment: (ply % 3 != 0).then(|| (ply * 7 - 20).to_string()),
+                });
+            }
+            record.result = GameResult::Draw;
+            let text = record.to_string();
+            assert!(text.lines().all(|line| line.len() <= 80));
+            assert_eq!(text.parse::<GameRecord>(), Ok(record));
+        }
+    }
+
+    #[test]
+    fn brackets_in_tag_values() {
+        let games = read_games("[Event \"Cup [final]\"]\n[Black \"one\"]\n\n1. b6 *").unwrap();
+        assert_eq!(games[0].event, "Cup [final]");
+        assert_eq!(games[0].black, "one");
+
+        // the writer leaves brackets alone, so they have to survive a round trip
+        let mut record = GameRecord::new(Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string()));
+        record.event = "Cup [final]".to_string();
+        record.black = "a \"]\" b \\".to_string();
+        record.white = "]".to_string();
+        assert_eq!(record.to_string().parse::<GameRecord>(), Ok(record));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let illegal = "1. a1a3 *";
+        assert!(matches!(
+            read_games(illegal),
+            Err(PgnError::Move {
+                ply: 1,
+                err: MoveError::NotOurStone(_),
+                ..
+            })
+        ));
+        assert_eq!(
+            read_games("1. f1 {score"),
+            Err(PgnError::UnterminatedComment)
+        );
+        assert_eq!(
+            read_games("[Event unquoted]"),
+            Err(PgnError::InvalidTag("Event unquoted".to_string()))
+        );
+        assert_eq!(
+            read_games("[Result \"2-0\"]"),
+            Err(PgnError::InvalidResult("2-0".to_string()))
+        );
+        assert!(matches!(
+            read_games("[FEN \"x5o/7/9 x\"]"),
+            Err(PgnError::Fen(_))
+        ));
+        assert_eq!("".parse::<GameRecord>(), Err(PgnError::NoGame));
+    }
+
+    #[test]
+    fn result_matches_status() {
+        for side in [Side::Black, Side::White] {
+            for status in [Status::Winner, Status::Loser, Status::Draw, Status::Ongoing] {
+                assert_eq!(GameResult::from_status(status, side).status(side), status);
+            }
+        }
+        assert_eq!(
+            GameResult::from_status(Status::Winner, Side::White),
+            GameResult::WhiteWins
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    board::{Board, Status},
+    move_app::{make_move, unmake_move},
+    movegen::{Move, MoveKind},
+};
+
+/// A board together with the moves that led to it, used to detect repeated positions
+#[derive(Clone, Debug)]
+pub struct Position {
+    board: Board,
+    history: Vec<Undo>,
+}
+
+/// Everything needed to take back a move
+#[derive(Clone, Copy, Debug)]
+struct Undo {
+    mov: Move,
+    half_move: u8,
+    /// Zobrist key of the position before the move was made
+    hash: u64,
+}
+
+impl Position {
+    pub fn new(board: Board) -> Position {
+        Position {
+            board,
+            history: vec![],
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The moves played since the position was created, oldest first
+    pub fn moves(&self) -> impl DoubleEndedIterator<Item = &Move> {
+        self.history.iter().map(|undo| &undo.mov)
+    }
+
+    pub fn make(&mut self, mov: &Move) {
+        let hash = self.board.hash;
+        let half_move = make_move(&mut self.board, mov);
+        self.history.push(Undo {
+            mov: *mov,
+            half_move,
+            hash,
+        });
+    }
+
+    /// Take back the last move, returning it, or `None` if no moves have been made
+    pub fn unmake(&mut self) -> Option<Move> {
+        let undo = self.history.pop()?;
+        unmake_move(&mut self.board, &undo.mov, undo.half_move);
+        Some(undo.mov)
+    }
+
+    /// Has the current position occurred at least `n` times, counting itself
+    pub fn is_repetition(&self, n: usize) -> bool {
+        let mut count = 1;
+        for undo in self.history.iter().rev() {
+            // a single move adds a stone, so nothing before it can match the current position
+            if undo.mov.kind == MoveKind::Single {
+                break;
+            }
+            if undo.hash == self.board.hash {
+                count += 1;
+                if count >= n {
+                    return true;
+                }
+            }
+        }
+        count >= n
+    }
+
+    /// Status of the side to move, treating a threefold repetition as a draw
+    pub fn status(&self) -> Status {
+        if self.is_repetition(3) {
+            Status::Draw
+        } else {
+            self.board.status()
+        }
+    }
+
+    pub fn game_over(&self) -> bool {
+        self.is_repetition(3) || self.board.game_over()
+    }
+}
+
+impl From<Board> for Position {
+    fn from(board: Board) -> Self {
+        Position::new(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Board, Status};
+
+    use super::Position;
+
+    fn play(position: &mut Position, moves: &[&str]) {
+        for mov in moves {
+            let mov = position.board().parse_move(mov).unwrap();
+            position.make(&mov);
+        }
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut position = Position::new(Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string()));
+        // shuffle both sides back and forth with double moves
+        let shuffle = ["a7a5", "g7g5", "a5a7", "g5g7"];
+        play(&mut position, &shuffle);
+        assert!(position.is_repetition(2));
+        assert!(!position.is_repetition(3));
+        assert_eq!(position.status(), Status::Ongoing);
+
+        play(&mut position, &shuffle);
+        assert!(position.is_repetition(3));
+        assert_eq!(position.status(), Status::Draw);
+        assert!(position.game_over());
+
+        position.unmake();
+        assert!(!position.is_repetition(3));
+        assert_eq!(position.status(), Status::Ongoing);
+    }
+
+    #[test]
+    fn repetition_after_single_move() {
+        let mut position = Position::new(Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string()));
+        // the scan stops at the single move but still sees the position right after it
+        play(&mut position, &["b6", "g7g5", "b6b4", "g5g7", "b4b6"]);
+        assert!(position.is_repetition(2));
+        assert!(!position.is_repetition(3));
+        play(&mut position, &["g7g5", "b6b4", "g5g7", "b4b6"]);
+        assert!(position.is_repetition(3));
+    }
+
+    #[test]
+    fn unmake_restores_board() {
+        let start = Board::new("x5o/7/2-1-2/7/2-1-2/7/o5x x 0 1".to_string());
+        let mut position = Position::new(start);
+        play(&mut position, &["b6", "f6", "b6d4"]);
+        assert_eq!(position.moves().count(), 3);
+        while position.unmake().is_some() {}
+        assert_eq!(*position.board(), start);
+    }
+}
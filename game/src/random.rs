@@ -0,0 +1,213 @@
+use crate::{
+    board::{BitBoard, Board, Side},
+    geometry::Geometry,
+    move_app::make_move,
+    movegen::generate_moves_into,
+    movelist::MoveList,
+    square::Square,
+    symmetry::Symmetry,
+    zobrist::splitmix64,
+};
+
+/// Gap layouts tried by [`random_position`] before it gives up on getting `plies` deep
+const MAX_ATTEMPTS: u32 = 1000;
+
+/// Black and white stones of the starting position, black starts in the top left and bottom right
+/// corners and white in the other two. `None` on a single file or rank, where the corners of the
+/// two sides are the same squares
+pub fn start_stones(geometry: Geometry) -> Option<[BitBoard; 2]> {
+    if geometry.files() < 2 || geometry.ranks() < 2 {
+        return None;
+    }
+    let (last_file, last_rank) = (geometry.files() - 1, geometry.ranks() - 1);
+    let corner = |file: u8, rank: u8| Square::new(file, rank).bitboard();
+    Some([
+        corner(0, last_rank) | corner(last_file, 0),
+        corner(0, 0) | corner(last_file, last_rank),
+    ])
+}
+
+/// Seeded splitmix64 generator, so generated positions can be reproduced
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let value;
+        (self.state, value) = splitmix64(self.state);
+        value
+    }
+
+    /// Uniform number in `0..bound`, `bound` must be non zero
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // multiply shift keeps the bias negligible for the small bounds used here
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// True with probability `p`
+    pub fn chance(&mut self, p: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+}
+
+/// Random gap layout where each square is a gap with probability `density`.
+///
+/// The layout is symmetric under mirroring the files and the ranks, which swaps the colours of
+/// the starting stones, so neither side is favoured. The starting corners are never gaps.
+pub fn random_gaps(rng: &mut Rng, geometry: Geometry, density: f64) -> BitBoard {
+    let mut gaps = 0;
+    // pick from the bottom left quadrant, a1-d4 on 7x7, and mirror into the rest of the board
+    for rank in 0..geometry.ranks().div_ceil(2) {
+        for file in 0..geometry.files().div_ceil(2) {
+            if rng.chance(density) {
+                gaps |= Square::new(file, rank).bitboard();
+            }
+        }
+    }
+    for symmetry in [
+        Symmetry::FlipFiles,
+        Symmetry::FlipRanks,
+        Symmetry::Rotate180,
+    ] {
+        gaps |= symmetry.bitboard(gaps, geometry);
+    }
+    let [black, white] = start_stones(geometry).unwrap_or_default();
+    gaps & !(black | white)
+}
+
+/// The starting position of a board of `geometry` with the given gaps, `None` if the board has no
+/// room for the starting stones
+pub fn start_position(geometry: Geometry, gaps: BitBoard) -> Option<Board> {
+    let mut board = Board {
+        blockers: gaps,
+        boards: start_stones(geometry)?,
+        side_to_move: Side::Black,
+        half_move: 0,
+        full_move: 1,
+        hash: 0,
+        geometry,
+    };
+    board.hash = board.compute_hash();
+    Some(board)
+}
+
+/// Play `plies` uniformly random legal moves from `start`, or `None` if the game ends first
+pub fn random_playout(rng: &mut Rng, start: &Board, plies: u32) -> Option<Board> {
+    let mut board = *start;
+    let mut moves = MoveList::new();
+    for _ in 0..plies {
+        generate_moves_into(&board, &mut moves);
+        if moves.is_empty() {
+            return None;
+        }
+        let mov = moves[rng.below(moves.len() as u64) as usize];
+        make_move(&mut board, &mov);
+    }
+    (!board.game_over()).then_some(board)
+}
+
+/// Random ongoing position `plies` moves into a game on a random gap layout, or `None` if every
+/// attempt ended the game sooner, as it always does when `plies` is too deep for the board
+pub fn random_position(
+    rng: &mut Rng,
+    geometry: Geometry,
+    plies: u32,
+    gap_density: f64,
+) -> Option<Board> {
+    (0..MAX_ATTEMPTS).find_map(|_| {
+        let start = start_position(geometry, random_gaps(rng, geometry, gap_density))?;
+        random_playout(rng, &start, plies)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::Board, geometry::Geometry, symmetry::Symmetry};
+
+    use super::{random_gaps, random_position, start_position, Rng};
+
+    #[test]
+    fn start_matches_fen() {
+        for (files, ranks, fen) in [
+            (7, 7, "x5o/7/7/7/7/7/o5x x 0 1"),
+            (5, 5, "x3o/5/5/5/o3x x 0 1"),
+            (6, 4, "x4o/6/6/o4x x 0 1"),
+        ] {
+            let geometry = Geometry::new(files, ranks).unwrap();
+            assert_eq!(
+                start_position(geometry, 0),
+                Some(Board::new(fen.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn thin_boards_have_no_start() {
+        for (files, ranks) in [(1, 1), (1, 3), (7, 1)] {
+            let geometry = Geometry::new(files, ranks).unwrap();
+            assert_eq!(start_position(geometry, 0), None);
+            assert!(random_position(&mut Rng::new(0), geometry, 0, 0.0).is_none());
+        }
+        assert!(start_position(Geometry::new(2, 3).unwrap(), 0).is_some());
+    }
+
+    #[test]
+    fn reproducible_from_seed() {
+        let positions = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..20)
+                .map(|_| random_position(&mut rng, Geometry::STANDARD, 6, 0.2).unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(positions(7), positions(7));
+        assert_ne!(positions(7), positions(8));
+    }
+
+    #[test]
+    fn gaps_are_symmetric() {
+        let mut rng = Rng::new(1);
+        for geometry in [
+            Geometry::STANDARD,
+            Geometry::new(6, 6).unwrap(),
+            Geometry::new(5, 4).unwrap(),
+        ] {
+            for _ in 0..100 {
+                let gaps = random_gaps(&mut rng, geometry, 0.3);
+                let start = start_position(geometry, 0).unwrap();
+                assert_eq!(gaps & (start.boards[0] | start.boards[1]), 0);
+                assert_eq!(gaps & !geometry.mask(), 0);
+                for symmetry in [Symmetry::FlipFiles, Symmetry::FlipRanks] {
+                    assert_eq!(symmetry.bitboard(gaps, geometry), gaps);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn positions_are_consistent() {
+        let mut rng = Rng::new(42);
+        for plies in 0..12 {
+            let geometry = Geometry::new(7 - plies as u8 % 3, 7).unwrap();
+            let board = random_position(&mut rng, geometry, plies, 0.15).unwrap();
+            assert!(!board.game_over());
+            assert_eq!(board.hash, board.compute_hash());
+            assert_eq!(board.full_move, 1 + plies / 2);
+            assert_eq!(Board::from_fen(&board.to_fen()), Ok(board));
+        }
+    }
+
+    #[test]
+    fn too_many_plies_gives_up() {
+        let mut rng = Rng::new(3);
+        // the board is full long before this on 7x7, and from the start on 2x2
+        assert!(random_position(&mut rng, Geometry::STANDARD, 400, 0.1).is_none());
+        let tiny = Geometry::new(2, 2).unwrap();
+        assert!(random_position(&mut rng, tiny, 1, 0.0).is_none());
+    }
+}
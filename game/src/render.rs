@@ -0,0 +1,151 @@
+use crate::{
+    board::{Board, Side},
+    movegen::Move,
+    square::Square,
+};
+
+/// Characters used to draw the board
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Charset {
+    /// `x`, `o` and `-` as in FEN, with `.` for empty squares
+    Ascii,
+    /// Filled and hollow circles for the stones and a shaded block for gaps
+    Unicode,
+}
+
+impl Charset {
+    fn black(self) -> char {
+        match self {
+            Charset::Ascii => 'x',
+            Charset::Unicode => '●',
+        }
+    }
+
+    fn white(self) -> char {
+        match self {
+            Charset::Ascii => 'o',
+            Charset::Unicode => '○',
+        }
+    }
+
+    fn gap(self) -> char {
+        match self {
+            Charset::Ascii => '-',
+            Charset::Unicode => '▒',
+        }
+    }
+
+    fn empty(self) -> char {
+        match self {
+            Charset::Ascii => '.',
+            Charset::Unicode => '·',
+        }
+    }
+}
+
+/// Draw the board as a grid with file and rank labels, followed by the side to move and counters.
+///
+/// With a `highlight` the from square of the move is wrapped in `( )`, the destination in `[ ]`
+/// and the captured stones in `< >`, e.g.
+///
+/// ```text
+///      a  b  c  d  e  f  g
+///   7  x  .  .  .  .  .  o  7
+///   6  .  .  .  .  .  .  .  6
+///   5  .  .  .  .  .  .  .  5
+///   4  .  .  .  .  .  .  .  4
+///   3  .  .  .  .  .  .  .  3
+///   2 [o] .  .  .  .  .  .  2
+///   1  o  .  .  .  .  .  x  1
+///      a  b  c  d  e  f  g
+///
+/// x to move, half move 1, full move 2
+/// move a2
+/// ```
+pub fn render(board: &Board, highlight: Option<&Move>, charset: Charset) -> String {
+    let geometry = board.geometry;
+    let files = (0..geometry.files())
+        .map(|file| format!(" {} ", (b'a' + file) as char))
+        .collect::<String>();
+    let files = format!("    {files}").trim_end().to_string();
+    let mut out = format!("{files}\n");
+    for rank in (0..geometry.ranks()).rev() {
+        out += &format!("{:>3} ", rank + 1);
+        for file in 0..geometry.files() {
+            let square = Square::new(file, rank).bitboard();
+            let piece = if board.boards[0] & square != 0 {
+                charset.black()
+            } else if board.boards[1] & square != 0 {
+                charset.white()
+            } else if board.blockers & square != 0 {
+                charset.gap()
+            } else {
+                charset.empty()
+            };
+            let (open, close) = match highlight {
+                Some(mov) if mov.to & square != 0 => ('[', ']'),
+                Some(mov) if mov.from & square != 0 => ('(', ')'),
+                Some(mov) if mov.capture_square & square != 0 => ('<', '>'),
+                _ => (' ', ' '),
+            };
+            out += &format!("{open}{piece}{close}");
+        }
+        out += &format!(" {}\n", rank + 1);
+    }
+    out += &format!("{files}\n\n");
+
+    let side = match board.side_to_move {
+        Side::Black => charset.black(),
+        Side::White => charset.white(),
+    };
+    out += &format!(
+        "{side} to move, half move {}, full move {}\n",
+        board.half_move, board.full_move
+    );
+    if let Some(mov) = highlight {
+        out += &format!("move {mov}\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::Board, move_app::make_move};
+
+    use super::{render, Charset};
+
+    #[test]
+    fn ascii_grid() {
+        let board = Board::new("x3o/5/1-3/5/o3x o 3 7".to_string());
+        let expected = [
+            "     a  b  c  d  e",
+            "  5  x  .  .  .  o  5",
+            "  4  .  .  .  .  .  4",
+            "  3  .  -  .  .  .  3",
+            "  2  .  .  .  .  .  2",
+            "  1  o  .  .  .  x  1",
+            "     a  b  c  d  e",
+            "",
+            "o to move, half move 3, full move 7",
+            "",
+        ];
+        assert_eq!(render(&board, None, Charset::Ascii), expected.join("\n"));
+    }
+
+    #[test]
+    fn highlights_move() {
+        let mut board = Board::new("x5o/7/7/1o5/7/7/o5x x 0 1".to_string());
+        let mov = board.parse_move("a7b5").unwrap();
+        make_move(&mut board, &mov);
+        let out = render(&board, Some(&mov), Charset::Ascii);
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], "  7 (.) .  .  .  .  .  o  7");
+        assert_eq!(lines[3], "  5  . [x] .  .  .  .  .  5");
+        assert_eq!(lines[4], "  4  . <x> .  .  .  .  .  4");
+        assert_eq!(lines.last(), Some(&"move a7b5"));
+
+        let out = render(&board, Some(&mov), Charset::Unicode);
+        assert!(out.contains("[●]") && out.contains("<●>") && out.contains("(·)"));
+        assert!(out.contains("○ to move"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Standard Ataxx termination rules.
+//!
+//! The game ends when a side has no stones left, after 100 half moves without a capture, or when
+//! neither side can move (which includes a full board). In the last case the side with more
+//! stones wins and equal counts are a draw. A side that cannot move while its opponent can has to
+//! pass, and the game goes on.
+
+use crate::{
+    board::{BitBoard, Board, Status},
+    movegen::{doubles, singles},
+};
+
+/// Number of half moves without a capture after which the game is drawn
+pub const HALF_MOVE_LIMIT: u8 = 100;
+
+/// Why a game ended
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Termination {
+    /// One or both sides have no stones left
+    Elimination,
+    /// The 50 move rule
+    HalfMoveLimit,
+    /// Neither side has a legal move, usually because the board is full
+    NoMoves,
+}
+
+/// Can a side with `stones` reach any of the `empty` squares
+pub fn can_move(stones: BitBoard, empty: BitBoard) -> bool {
+    (singles(stones) | doubles(stones)) & empty != 0
+}
+
+/// Why the game is over, or `None` if it is still going on
+pub fn termination(board: &Board) -> Option<Termination> {
+    let us = board.current_pieces();
+    let them = board.other_pieces();
+
+    if us == 0 || them == 0 {
+        Some(Termination::Elimination)
+    } else if board.half_move >= HALF_MOVE_LIMIT {
+        Some(Termination::HalfMoveLimit)
+    } else if !can_move(us, board.empty()) && !can_move(them, board.empty()) {
+        Some(Termination::NoMoves)
+    } else {
+        None
+    }
+}
+
+pub fn game_over(board: &Board) -> bool {
+    termination(board).is_some()
+}
+
+/// Result of the game from the point of view of the side to move
+pub fn status(board: &Board) -> Status {
+    match termination(board) {
+        None => Status::Ongoing,
+        Some(Termination::HalfMoveLimit) => Status::Draw,
+        Some(Termination::Elimination | Termination::NoMoves) => {
+            let us = board.current_pieces().count_ones();
+            let them = board.other_pieces().count_ones();
+            match us.cmp(&them) {
+                std::cmp::Ordering::Greater => Status::Winner,
+                std::cmp::Ordering::Less => Status::Loser,
+                std::cmp::Ordering::Equal => Status::Draw,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{Board, Status},
+        movegen::{generate_moves, MoveKind},
+    };
+
+    use super::{status, termination, Termination};
+
+    fn check(fen: &str, expected: Status, reason: Option<Termination>) {
+        let board = Board::new(fen.to_string());
+        assert_eq!(status(&board), expected, "fen: {fen}");
+        assert_eq!(termination(&board), reason, "fen: {fen}");
+        assert_eq!(board.status(), expected, "fen: {fen}");
+        assert_eq!(board.game_over(), reason.is_some(), "fen: {fen}");
+        // no moves are generated once the game is over
+        assert_eq!(
+            generate_moves(&board).is_empty(),
+            reason.is_some(),
+            "fen: {fen}"
+        );
+    }
+
+    #[test]
+    fn ongoing() {
+        check("x5o/7/7/7/7/7/o5x x 0 1", Status::Ongoing, None);
+        check("x5o/7/2-1-2/3-3/2-1-2/7/o5x o 99 50", Status::Ongoing, None);
+    }
+
+    #[test]
+    fn elimination() {
+        let white_only = "6o/7/2o4/3o3/4o2/7/o6";
+        check(
+            &format!("{white_only} o 0 1"),
+            Status::Winner,
+            Some(Termination::Elimination),
+        );
+        check(
+            &format!("{white_only} x 0 1"),
+            Status::Loser,
+            Some(Termination::Elimination),
+        );
+        check(
+            "7/7/7/7/7/7/7 x 0 1",
+            Status::Draw,
+            Some(Termination::Elimination),
+        );
+    }
+
+    #[test]
+    fn half_move_limit() {
+        check(
+            "x5o/7/7/7/7/7/o5x x 100 1",
+            Status::Draw,
+            Some(Termination::HalfMoveLimit),
+        );
+        check("x5o/7/7/7/7/7/o5x x 99 1", Status::Ongoing, None);
+        // elimination takes priority over the 50 move rule
+        check(
+            "x6/7/7/7/7/7/7 o 100 1",
+            Status::Loser,
+            Some(Termination::Elimination),
+        );
+    }
+
+    #[test]
+    fn full_board() {
+        let full = "xxxxxxx/xxxxxxx/xxxxxxx/xxxxooo/ooooooo/ooooooo/ooooooo";
+        check(
+            &format!("{full} x 0 1"),
+            Status::Winner,
+            Some(Termination::NoMoves),
+        );
+        check(
+            &format!("{full} o 0 1"),
+            Status::Loser,
+            Some(Termination::NoMoves),
+        );
+        // equal counts are a draw
+        check(
+            "xxxxxxx/xxxxxxx/xxxxxxx/xxx-ooo/ooooooo/ooooooo/ooooooo o 0 1",
+            Status::Draw,
+            Some(Termination::NoMoves),
+        );
+    }
+
+    #[test]
+    fn unreachable_empty_squares() {
+        // the empty squares on ranks 4 to 7 are walled off by two ranks of gaps
+        check(
+            "7/7/7/7/-------/-------/xxxxooo o 0 1",
+            Status::Loser,
+            Some(Termination::NoMoves),
+        );
+        check(
+            "7/7/7/7/-------/-------/xxx1ooo x 0 1",
+            Status::Ongoing,
+            None,
+        );
+    }
+
+    #[test]
+    fn pass() {
+        // black is blocked in but white can still move, so black has to pass
+        let fen = "7/7/7/7/ooooooo/ooooooo/xxxxxxx x 0 1";
+        check(fen, Status::Ongoing, None);
+        let moves = generate_moves(&Board::new(fen.to_string()));
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].kind, MoveKind::Pass);
+    }
+}
@@ -0,0 +1,125 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use crate::board::BitBoard;
+
+/// A square on the board, stored as its bit index (`rank * 8 + file`, a1 is 0)
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub struct Square(u8);
+
+impl Square {
+    /// Square from a zero indexed file and rank
+    pub const fn new(file: u8, rank: u8) -> Square {
+        debug_assert!(file < 8 && rank < 8);
+        Square(rank * 8 + file)
+    }
+
+    /// Square from its bit index
+    pub const fn from_index(index: u8) -> Square {
+        debug_assert!(index < 64);
+        Square(index)
+    }
+
+    /// The lowest set square of a non empty bitboard
+    pub const fn from_bitboard(bb: BitBoard) -> Square {
+        debug_assert!(bb != 0);
+        Square(bb.trailing_zeros() as u8)
+    }
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub const fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    pub const fn rank(self) -> u8 {
+        self.0 / 8
+    }
+
+    pub const fn bitboard(self) -> BitBoard {
+        1 << self.0
+    }
+}
+
+/// Error returned when a string is not an algebraic square on the 7x7 board
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseSquareError(pub String);
+
+impl Display for ParseSquareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid square '{}'", self.0)
+    }
+}
+
+impl Error for ParseSquareError {}
+
+impl FromStr for Square {
+    type Err = ParseSquareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            [file @ b'a'..=b'g', rank @ b'1'..=b'7'] => Ok(Square::new(file - b'a', rank - b'1')),
+            _ => Err(ParseSquareError(s.to_string())),
+        }
+    }
+}
+
+impl Display for Square {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", (b'a' + self.file()) as char, self.rank() + 1)
+    }
+}
+
+/// Iterator over the set squares of a bitboard, lowest first
+#[derive(Clone, Copy, Debug)]
+pub struct BitIter(pub BitBoard);
+
+impl Iterator for BitIter {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0 == 0 {
+            return None;
+        }
+        let square = Square::from_bitboard(self.0);
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.0.count_ones() as usize;
+        (count, Some(count))
+    }
+}
+
+impl ExactSizeIterator for BitIter {}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitIter, Square};
+
+    #[test]
+    fn algebraic_round_trip() {
+        for rank in 0..7 {
+            for file in 0..7 {
+                let square = Square::new(file, rank);
+                assert_eq!(square.to_string().parse(), Ok(square));
+            }
+        }
+        assert_eq!("d3".parse::<Square>().unwrap().bitboard(), 0x80000);
+        assert!("h1".parse::<Square>().is_err());
+        assert!("a8".parse::<Square>().is_err());
+        assert!("a".parse::<Square>().is_err());
+    }
+
+    #[test]
+    fn iterates_set_bits() {
+        let squares = BitIter(0x40000000000041).collect::<Vec<_>>();
+        assert_eq!(
+            squares,
+            ["a1", "g1", "g7"].map(|square| square.parse::<Square>().unwrap())
+        );
+        assert_eq!(BitIter(0).next(), None);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    board::{BitBoard, Board},
+    geometry::Geometry,
+    movegen::Move,
+    square::{BitIter, Square},
+};
+
+/// The 8 symmetries of a square board, rotations are clockwise with the last rank at the top
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirror the files, a1 <-> g1
+    FlipFiles,
+    /// Mirror the ranks, a1 <-> a7
+    FlipRanks,
+    /// Reflect in the a1-g7 diagonal, b1 <-> a2
+    FlipDiagonal,
+    /// Reflect in the a7-g1 diagonal, a1 <-> g7
+    FlipAntiDiagonal,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipFiles,
+        Symmetry::FlipRanks,
+        Symmetry::FlipDiagonal,
+        Symmetry::FlipAntiDiagonal,
+    ];
+
+    /// The symmetry that undoes this one
+    pub fn inverse(self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => other,
+        }
+    }
+
+    /// Does this symmetry map a board of `geometry` onto itself, quarter turns and diagonal
+    /// reflections only work on square boards
+    pub fn fits(self, geometry: Geometry) -> bool {
+        match self {
+            Symmetry::Identity
+            | Symmetry::Rotate180
+            | Symmetry::FlipFiles
+            | Symmetry::FlipRanks => true,
+            _ => geometry.is_square(),
+        }
+    }
+
+    /// Where `square` goes on a board of `geometry`
+    pub fn square(self, square: Square, geometry: Geometry) -> Square {
+        debug_assert!(geometry.contains(square) && self.fits(geometry));
+        let (file, rank) = (square.file(), square.rank());
+        let (last_file, last_rank) = (geometry.files() - 1, geometry.ranks() - 1);
+        let (file, rank) = match self {
+            Symmetry::Identity => (file, rank),
+            Symmetry::Rotate90 => (rank, last_file - file),
+            Symmetry::Rotate180 => (last_file - file, last_rank - rank),
+            Symmetry::Rotate270 => (last_rank - rank, file),
+            Symmetry::FlipFiles => (last_file - file, rank),
+            Symmetry::FlipRanks => (file, last_rank - rank),
+            Symmetry::FlipDiagonal => (rank, file),
+            Symmetry::FlipAntiDiagonal => (last_rank - rank, last_file - file),
+        };
+        Square::new(file, rank)
+    }
+
+    pub fn bitboard(self, bb: BitBoard, geometry: Geometry) -> BitBoard {
+        BitIter(bb).fold(0, |out, square| {
+            out | self.square(square, geometry).bitboard()
+        })
+    }
+}
+
+impl Board {
+    /// The board with every stone and gap moved by `symmetry`, which has to fit the board
+    pub fn transform(&self, symmetry: Symmetry) -> Board {
+        assert!(
+            symmetry.fits(self.geometry),
+            "{symmetry:?} does not fit a {} board",
+            self.geometry
+        );
+        let mut out = Board {
+            blockers: symmetry.bitboard(self.blockers, self.geometry),
+            boards: self
+                .boards
+                .map(|board| symmetry.bitboard(board, self.geometry)),
+            ..*self
+        };
+        out.hash = out.compute_hash();
+        out
+    }
+
+    /// The smallest of the symmetric versions of this board, and the symmetry that produces it.
+    ///
+    /// Boards are compared by gaps, then black stones, then white stones, so all symmetric
+    /// positions share the same canonical board. Square boards have 8 symmetries, other
+    /// rectangles only 4.
+    pub fn canonical(&self) -> (Board, Symmetry) {
+        Symmetry::ALL
+            .iter()
+            .filter(|symmetry| symmetry.fits(self.geometry))
+            .map(|&symmetry| (self.transform(symmetry), symmetry))
+            .min_by_key(|(board, _)| (board.blockers, board.boards))
+            .unwrap()
+    }
+}
+
+impl Move {
+    /// The same move on a board of `geometry` transformed by `symmetry`
+    pub fn transform(&self, symmetry: Symmetry, geometry: Geometry) -> Move {
+        Move {
+            kind: self.kind,
+            from: symmetry.bitboard(self.from, geometry),
+            to: symmetry.bitboard(self.to, geometry),
+            capture_square: symmetry.bitboard(self.capture_square, geometry),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::Board, geometry::Geometry, movegen::generate_moves, square::Square};
+
+    use super::Symmetry;
+
+    const FENS: [&str; 6] = [
+        "x5o/7/7/7/7/7/o5x x 0 1",
+        "x5o/7/2-1-2/3-3/2-1-2/7/o5x o 0 1",
+        "7/7/7/2x1o2/7/7/7 x 0 1",
+        "1x2--o/-6/7/3x3/o6/2-3o/x6 o 3 12",
+        "x2-o/1o3/5/-4/ox3 x 2 4",
+        "x4o/1-4/3x1o/o4x o 0 1",
+    ];
+
+    fn symmetries(board: &Board) -> impl Iterator<Item = Symmetry> + '_ {
+        Symmetry::ALL
+            .into_iter()
+            .filter(|symmetry| symmetry.fits(board.geometry))
+    }
+
+    #[test]
+    fn known_squares() {
+        let square = |square: &str| square.parse::<Square>().unwrap();
+        let standard = Geometry::STANDARD;
+        let small = Geometry::new(5, 5).unwrap();
+        let wide = Geometry::new(6, 4).unwrap();
+        let cases = [
+            (standard, Symmetry::Rotate90, "a1", "a7"),
+            (standard, Symmetry::Rotate90, "b1", "a6"),
+            (standard, Symmetry::Rotate180, "b1", "f7"),
+            (standard, Symmetry::Rotate270, "a1", "g1"),
+            (standard, Symmetry::FlipFiles, "b3", "f3"),
+            (standard, Symmetry::FlipRanks, "b3", "b5"),
+            (standard, Symmetry::FlipDiagonal, "b1", "a2"),
+            (standard, Symmetry::FlipAntiDiagonal, "a1", "g7"),
+            (small, Symmetry::Rotate90, "b1", "a4"),
+            (small, Symmetry::FlipAntiDiagonal, "a1", "e5"),
+            (wide, Symmetry::Rotate180, "b1", "e4"),
+            (wide, Symmetry::FlipRanks, "c2", "c3"),
+        ];
+        for (geometry, symmetry, from, to) in cases {
+            assert_eq!(
+                symmetry.square(square(from), geometry),
+                square(to),
+                "{geometry} {symmetry:?}"
+            );
+        }
+        assert!(!Symmetry::Rotate90.fits(wide));
+        assert!(!Symmetry::FlipDiagonal.fits(wide));
+    }
+
+    #[test]
+    fn inverse_restores_board() {
+        for fen in FENS {
+            let board = Board::new(fen.to_string());
+            for symmetry in symmetries(&board) {
+                let transformed = board.transform(symmetry);
+                assert_eq!(transformed.hash, transformed.compute_hash());
+                assert_eq!(transformed.transform(symmetry.inverse()), board);
+            }
+        }
+    }
+
+    #[test]
+    fn moves_commute_with_transform() {
+        for fen in FENS {
+            let board = Board::new(fen.to_string());
+            for symmetry in symmetries(&board) {
+                let mut expected = generate_moves(&board)
+                    .iter()
+                    .map(|mov| mov.transform(symmetry, board.geometry))
+                    .collect::<Vec<_>>();
+                let mut actual = generate_moves(&board.transform(symmetry));
+                expected.sort();
+                actual.sort();
+                assert_eq!(actual, expected, "fen: {fen}, {symmetry:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_is_shared() {
+        for fen in FENS {
+            let board = Board::new(fen.to_string());
+            let (canonical, symmetry) = board.canonical();
+            assert_eq!(board.transform(symmetry), canonical);
+            for other in symmetries(&board) {
+                assert_eq!(board.transform(other).canonical().0, canonical);
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    board::{BitBoard, Side},
+    square::BitIter,
+};
+
+/// Random keys for every (side, square) pair, indexed by `[side][square]`
+pub const STONE_KEYS: [[u64; 64]; 2] = {
+    let mut keys = [[0; 64]; 2];
+    let mut state = SEED;
+    let mut side = 0;
+    while side < 2 {
+        let mut square = 0;
+        while square < 64 {
+            (state, keys[side][square]) = splitmix64(state);
+            square += 1;
+        }
+        side += 1;
+    }
+    keys
+};
+
+/// Random keys for every gap square, so identical stones on different gap layouts hash differently
+pub const GAP_KEYS: [u64; 64] = {
+    let mut keys = [0; 64];
+    // offset the seed so these keys dont overlap with the stone keys
+    let mut state = SEED ^ 0x5851_f42d_4c95_7f2d;
+    let mut square = 0;
+    while square < 64 {
+        (state, keys[square]) = splitmix64(state);
+        square += 1;
+    }
+    keys
+};
+
+/// Key that is xored in whenever white is the side to move
+pub const SIDE_KEY: u64 = splitmix64(SEED ^ 0x2545_f491_4f6c_dd1d).1;
+
+const SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Step the splitmix64 generator, returning the new state and the output
+pub(crate) const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+/// Xor of the stone keys of every square in `bb` for `side`
+pub fn stone_keys(side: Side, bb: BitBoard) -> u64 {
+    BitIter(bb).fold(0, |key, square| {
+        key ^ STONE_KEYS[side as usize][square.index()]
+    })
+}
+
+/// Xor of the gap keys of every square in `bb`
+pub fn gap_keys(bb: BitBoard) -> u64 {
+    BitIter(bb).fold(0, |key, square| key ^ GAP_KEYS[square.index()])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::Board,
+        move_app::{make_move, unmake_move},
+        movegen::generate_moves,
+    };
+
+    /// Walk the perft tree checking the incremental key against a full recomputation
+    fn check_tree(board: &mut Board, depth: u8) {
+        assert_eq!(board.hash, board.compute_hash(), "fen: {board}");
+        if depth == 0 {
+            return;
+        }
+        for mov in &generate_moves(board) {
+            let old_hash = board.hash;
+            let delta = make_move(board, mov);
+            check_tree(board, depth - 1);
+            unmake_move(board, mov, delta);
+            assert_eq!(board.hash, old_hash);
+        }
+    }
+
+    #[test]
+    fn incremental_matches_scratch() {
+        for fen in [
+            "x5o/7/7/7/7/7/o5x x 0 1",
+            "x5o/7/2-1-2/3-3/2-1-2/7/o5x o 0 1",
+            "7/7/7/2x1o2/7/7/7 x 0 1",
+            "7/7/7/7/ooooooo/ooooooo/xxxxxxx x 0 1",
+            "7/7/7/7/-------/-------/x5o x 0 1",
+        ] {
+            check_tree(&mut Board::new(fen.to_string()), 4);
+        }
+    }
+
+    #[test]
+    fn distinguishes_positions() {
+        let hashes = [
+            "x5o/7/7/7/7/7/o5x x 0 1",
+            "x5o/7/7/7/7/7/o5x o 0 1",
+            "o5x/7/7/7/7/7/x5o x 0 1",
+            "x5o/7/3-3/7/7/7/o5x x 0 1",
+        ]
+        .map(|fen| Board::new(fen.to_string()).hash);
+        for (i, a) in hashes.iter().enumerate() {
+            for b in &hashes[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        // move counters dont affect the key
+        assert_eq!(
+            Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string()).hash,
+            Board::new("x5o/7/7/7/7/7/o5x x 20 11".to_string()).hash
+        );
+    }
+}
@@ -1,10 +1,18 @@
+use game::{
+    board::Board,
+    perft::{perft_divide, perft_parallel_divide, tag_stats},
+    render::{render, Charset},
+};
 use search::Search;
-use search::{GoInfo, Shared};
+use search::{GoInfo, Shared, DEFAULT_HASH_MB, SEARCH_STACK_SIZE};
 
 use std::{
+    env,
     io::{self},
-    sync::{mpsc::channel, Arc, Mutex},
+    process,
+    sync::{atomic::Ordering, mpsc::channel, Arc},
     thread,
+    time::Instant,
 };
 use text_io::read;
 
@@ -16,7 +24,97 @@ fn get_input() -> String {
     input
 }
 
+/// `sea_baxx perft <depth> [fen] [--threads N] [--hash MB] [--tags]`: print the node count below every root move, the total and the speed,
+/// and with `--tags` how many of the moves are equivalent or dominated doubles
+fn run_perft(args: &[String]) {
+    let usage = || -> ! {
+        eprintln!("usage: sea_baxx perft <depth> [fen] [--threads N] [--hash MB] [--tags]");
+        process::exit(1);
+    };
+    // pull the options out, everything after the depth that is left is the fen
+    let mut args = args.to_vec();
+    let tags = match args.iter().position(|arg| arg == "--tags") {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    };
+    let mut take_option = |name: &str, default: usize| -> usize {
+        match args.iter().position(|arg| arg == name) {
+            Some(index) if index + 1 < args.len() => {
+                let value = args[index + 1].parse().unwrap_or_else(|_| usage());
+                args.drain(index..index + 2);
+                value
+            }
+            Some(_) => usage(),
+            None => default,
+        }
+    };
+    let threads = take_option("--threads", 1);
+    let hash_mb = take_option("--hash", 0);
+    let Some(depth) = args.first().and_then(|depth| depth.parse::<u8>().ok()) else {
+        usage();
+    };
+    let fen = if args.len() > 1 {
+        args[1..].join(" ")
+    } else {
+        "x5o/7/7/7/7/7/o5x x 0 1".to_string()
+    };
+    let mut board = Board::from_fen(&fen).unwrap_or_else(|err| {
+        eprintln!("invalid fen: {err}");
+        process::exit(1);
+    });
+
+    let t0 = Instant::now();
+    let divide = if threads > 1 || hash_mb > 0 {
+        perft_parallel_divide(&board, depth, threads, hash_mb)
+    } else {
+        perft_divide(&mut board, depth)
+    };
+    let elapsed = t0.elapsed();
+
+    for (mov, nodes) in &divide {
+        println!("{mov}: {nodes}");
+    }
+    let nodes = if depth == 0 {
+        1
+    } else {
+        divide.iter().map(|(_, nodes)| nodes).sum::<u64>()
+    };
+    println!();
+    println!("nodes {nodes}");
+    println!("time {} ms", elapsed.as_millis());
+    println!(
+        "{:.2} Mnps",
+        nodes as f64 / elapsed.as_secs_f64().max(1e-9) / 1_000_000.0
+    );
+    if tags {
+        println!("{}", tag_stats(&mut board, depth));
+    }
+}
+
+/// Largest transposition table the `Hash` option allows, in megabytes
+const MAX_HASH_MB: usize = 65536;
+
+/// Most search threads the `Threads` option allows
+const MAX_THREADS: usize = 256;
+
+/// Split `setoption name <id> value <x>` into the name and the value
+fn parse_option(input: &str) -> Option<(&str, &str)> {
+    let rest = input.strip_prefix("setoption")?.trim_start();
+    let rest = rest.strip_prefix("name")?;
+    let (name, value) = rest.split_once(" value ")?;
+    Some((name.trim(), value.trim()))
+}
+
 fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if args.first().is_some_and(|command| command == "perft") {
+        run_perft(&args[1..]);
+        return;
+    }
+
     // wait for uai
 
     loop {
@@ -31,6 +129,8 @@ fn main() {
     println!("id name SeaBaxx");
     println!("id author BrokenKeyboard");
     // send options
+    println!("option name Hash type spin default {DEFAULT_HASH_MB} min 1 max {MAX_HASH_MB}");
+    println!("option name Threads type spin default 1 min 1 max {MAX_THREADS}");
 
     // uciok
     println!("uaiok");
@@ -47,29 +147,52 @@ fn main() {
     // }
     // setup my stuff
     let (send, recv) = channel::<SearchMessage>();
-    let shared = Arc::new(Mutex::new(Shared { stop: false }));
+    let shared = Arc::new(Shared::default());
     let shared_for_thread = Arc::clone(&shared);
-    thread::spawn(move || {
-        let mut search = Search::new(Arc::clone(&shared_for_thread));
-        while let Ok(message) = recv.recv() {
-            match message {
-                SearchMessage::NewGame => {
-                    shared_for_thread.lock().expect("error").stop = false;
-                    search.setup_newgame();
-                }
-                SearchMessage::Go(things) => {
-                    let (best_move, _) = search.find_best_move(true, &things);
-                    println!("bestmove {}", best_move);
-                }
-                SearchMessage::SetPosition(info) => {
-                    search.set_position(info);
-                }
-                SearchMessage::Ready => {
-                    println!("readyok");
+    let search_thread = thread::Builder::new().stack_size(SEARCH_STACK_SIZE);
+    search_thread
+        .spawn(move || {
+            let mut search = Search::new(Arc::clone(&shared_for_thread));
+            while let Ok(message) = recv.recv() {
+                match message {
+                    SearchMessage::NewGame => {
+                        shared_for_thread.stop.store(false, Ordering::Relaxed);
+                        search.setup_newgame();
+                    }
+                    SearchMessage::Go(things) => {
+                        let (best_move, _) = search.find_best_move(true, &things);
+                        // the reply we expect is the second move of the principal variation
+                        match search.pv().get(1) {
+                            Some(ponder) => println!("bestmove {best_move} ponder {ponder}"),
+                            None => println!("bestmove {best_move}"),
+                        }
+                    }
+                    SearchMessage::SetPosition(info) => {
+                        if let Err(err) = search.set_position(info) {
+                            println!("info string {err}");
+                        }
+                    }
+                    SearchMessage::SetHash(mb) => {
+                        search.set_hash(mb);
+                    }
+                    SearchMessage::SetThreads(threads) => {
+                        search.set_threads(threads);
+                    }
+                    SearchMessage::Ready => {
+                        println!("readyok");
+                    }
+                    SearchMessage::Display(charset) => {
+                        // highlight the move that led to the position
+                        let position = search.position();
+                        print!(
+                            "{}",
+                            render(position.board(), position.moves().next_back(), charset)
+                        );
+                    }
                 }
             }
-        }
-    });
+        })
+        .expect("unable to spawn the search thread");
     // send readyok
     // loop with a match for all the uai commands
     loop {
@@ -92,11 +215,35 @@ fn main() {
                 ))))
                 .unwrap(),
             "stop" => {
-                shared.lock().unwrap().stop = true;
+                shared.stop.store(true, Ordering::Relaxed);
             }
+            "setoption" => match parse_option(input) {
+                Some(("Hash", value)) => match value.parse::<usize>() {
+                    Ok(mb) => send
+                        .send(SearchMessage::SetHash(mb.clamp(1, MAX_HASH_MB)))
+                        .unwrap(),
+                    Err(_) => println!("info string invalid Hash value {value}"),
+                },
+                Some(("Threads", value)) => match value.parse::<usize>() {
+                    Ok(threads) => send
+                        .send(SearchMessage::SetThreads(threads.clamp(1, MAX_THREADS)))
+                        .unwrap(),
+                    Err(_) => println!("info string invalid Threads value {value}"),
+                },
+                Some((name, _)) => println!("info string unknown option {name}"),
+                None => println!("info string expected setoption name <id> value <x>"),
+            },
             "isready" => {
                 send.send(SearchMessage::Ready).unwrap();
             }
+            "d" => {
+                let charset = if input.contains("unicode") {
+                    Charset::Unicode
+                } else {
+                    Charset::Ascii
+                };
+                send.send(SearchMessage::Display(charset)).unwrap();
+            }
             "ponderhit" => todo!(),
             "quit" => {
                 break;
@@ -111,4 +258,10 @@ enum SearchMessage {
     SetPosition(String),
     Go(GoInfo),
     Ready,
+    /// Resize the transposition table to this many megabytes, clearing it
+    SetHash(usize),
+    /// Search with this many threads from the next `go` on
+    SetThreads(usize),
+    /// Print the current position, `d unicode` draws it with unicode stones
+    Display(Charset),
 }
@@ -1,23 +1,101 @@
 mod movepicker;
-mod table;
+pub mod table;
 
 use std::{
-    sync::{Arc, Mutex},
-    time::{Duration, Instant}, f32::MAX_10_EXP,
+    error::Error,
+    fmt::Display,
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use crate::movepicker::MovePicker;
 use crate::table::{Entry, NodeType, Table};
 use eval::Eval;
 use game::{
-    board::{Board, Side, Status},
-    move_app::{make_move, unmake_move},
-    movegen::{generate_moves, Move},
+    board::{Board, FenError, MoveError, Side, Status},
+    movegen::{generate_moves_into, Move, PackedMove},
+    movelist::MoveList,
+    position::Position,
 };
 
 const MAX_DEPTH : u8 = 200;
+/// Bound for alpha and beta, above any score
+const INFINITY: i32 = 100_000;
+/// Score of winning at the root, each ply to the win costs one point so quicker wins score higher
+const WIN_SCORE: i32 = 1000;
+/// Any score at least this large is a forced win
+const MIN_WIN_SCORE: i32 = WIN_SCORE - MAX_DEPTH as i32;
+
+/// Win scores are stored relative to the node so they stay correct when reached at another ply
+fn score_to_tt(score: i32, ply: u8) -> i32 {
+    if score >= MIN_WIN_SCORE {
+        score + ply as i32
+    } else if score <= -MIN_WIN_SCORE {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Undo [`score_to_tt`] for a node `ply` plies from the root
+fn score_from_tt(score: i32, ply: u8) -> i32 {
+    if score >= MIN_WIN_SCORE {
+        score - ply as i32
+    } else if score <= -MIN_WIN_SCORE {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// Why a `position` command could not be applied
+#[derive(Debug)]
+pub enum PositionError {
+    Fen(FenError),
+    Move(MoveError),
+}
+
+impl From<FenError> for PositionError {
+    fn from(err: FenError) -> Self {
+        PositionError::Fen(err)
+    }
+}
+
+impl From<MoveError> for PositionError {
+    fn from(err: MoveError) -> Self {
+        PositionError::Move(err)
+    }
+}
+
+impl Display for PositionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PositionError::Fen(err) => write!(f, "invalid fen: {err}"),
+            PositionError::Move(err) => write!(f, "invalid move: {err}"),
+        }
+    }
+}
+
+impl Error for PositionError {}
+
+/// Stack size for threads that run a search, every ply keeps its move lists on the stack
+pub const SEARCH_STACK_SIZE: usize = 32 * 1024 * 1024;
+/// Size of the transposition table in megabytes until the `Hash` option is set
+pub const DEFAULT_HASH_MB: usize = 16;
+/// Nodes a search counts before adding them to [`Shared::nodes`], so while helpers run the total
+/// is short by less than this for each of them
+const NODE_FLUSH: u64 = 1024;
+/// State every thread of a search can see
+#[derive(Default)]
 pub struct Shared {
-    pub stop: bool,
+    pub stop: AtomicBool,
+    /// nodes searched by all threads, each adds its count in batches
+    pub nodes: AtomicU64,
 }
 pub struct GoInfo {
     pub wtime: Option<u32>,
@@ -55,13 +133,7 @@ impl GoInfo {
             nodes: find_arg!(split, "nodes", u32),
             mate: find_arg!(split, "mate", u32),
             movetime: find_arg!(split, "movetime", u32),
-            infinite: {
-                if split.contains(&"infinite") {
-                    true
-                } else {
-                    false
-                }
-            },
+            infinite: split.contains(&"infinite"),
         };
         out
     }
@@ -69,39 +141,64 @@ impl GoInfo {
 
 pub struct Search {
     search_info: SearchInfo,
-    shared: Arc<Mutex<Shared>>,
-    table: Table,
-    board: Board,
+    shared: Arc<Shared>,
+    table: Arc<Table>,
+    /// searches that run on their own threads next to this one, sharing the table and stop flag
+    helpers: Vec<Search>,
+    position: Position,
     my_side: Side,
     stack_storage: Vec<SearchData>,
+    /// triangular pv table, `pv[ply]` is the best line found from the node `ply` plies deep
+    pv: Vec<Vec<Move>>,
+    /// principal variation of the last iteration that gave a best move
+    best_pv: Vec<Move>,
     eval: Eval,
+    /// prune with the scores in the table, not just order with its moves
+    tt_cutoffs: bool,
 }
 
 struct SearchInfo {
     nodes: u64,
-    tt_hits: u64,
-    cutoffs: u64,
+    /// deepest ply reached in the current iteration
+    seldepth: u8,
+    /// the end condition or a stop hit during the search, scores from then on are meaningless
+    stopped: bool,
+    /// score of the root move at the head of the principal variation, kept when a stop cuts the
+    /// iteration short
+    root_score: i32,
 }
 
 impl SearchInfo {
     fn new() -> SearchInfo {
         SearchInfo {
             nodes: 0,
-            tt_hits: 0,
-            cutoffs: 0,
+            seldepth: 0,
+            stopped: false,
+            root_score: 0,
         }
     }
 
     fn reset(&mut self) {
         self.nodes = 0;
-        self.tt_hits = 0;
-        self.cutoffs = 0;
+        self.seldepth = 0;
+        self.stopped = false;
+        self.root_score = 0;
     }
 }
 #[derive(Clone, Copy)]
 pub struct SearchData {
     killer_move: Option<Move>,
-    pv_move: Option<Move>,
+}
+
+/// Score in UAI form, `cp` for evaluations and `mate` in moves for forced wins and losses
+fn uai_score(score: i32) -> String {
+    if score >= MIN_WIN_SCORE {
+        format!("mate {}", (WIN_SCORE - score + 1) / 2)
+    } else if score <= -MIN_WIN_SCORE {
+        format!("mate -{}", (WIN_SCORE + score) / 2)
+    } else {
+        format!("cp {score}")
+    }
 }
 
 pub enum EndCondition {
@@ -122,65 +219,107 @@ impl EndCondition {
     }
 }
 impl Search {
-    pub fn new(shared: Arc<Mutex<Shared>>) -> Self {
+    pub fn new(shared: Arc<Shared>) -> Self {
+        Search::with_table(shared, Arc::new(Table::new(DEFAULT_HASH_MB)))
+    }
+    fn with_table(shared: Arc<Shared>, table: Arc<Table>) -> Self {
         Search {
             stack_storage: vec![
                 SearchData {
                     killer_move: None,
-                    pv_move: None
                 };
                 MAX_DEPTH as usize
             ],
+            // one line more than plies so the deepest node can read the empty line below it
+            pv: vec![Vec::with_capacity(MAX_DEPTH as usize); MAX_DEPTH as usize + 1],
+            best_pv: vec![],
             search_info: SearchInfo::new(),
-            table: Table::new(2_000_000),
-            shared: shared,
-            board: Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string()),
+            table,
+            helpers: vec![],
+            shared,
+            position: Position::new(Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string())),
             my_side: Side::Black,
             eval: Eval::new(),
+            tt_cutoffs: true,
         }
     }
     /// Clear hash and reset PV on new game
     pub fn setup_newgame(&mut self) {
         self.table.reset();
+        self.clear_stack();
+        self.helpers.iter_mut().for_each(Search::clear_stack);
+    }
+    fn clear_stack(&mut self) {
         self.stack_storage.iter_mut().for_each(|x| {
             *x = SearchData {
                 killer_move: None,
-                pv_move: None,
             }
         });
+        self.pv.iter_mut().for_each(Vec::clear);
+        self.best_pv.clear();
+    }
+    /// Search with `threads` threads in total, all but this one are helpers for lazy SMP
+    pub fn set_threads(&mut self, threads: usize) {
+        let helpers = threads.max(1) - 1;
+        self.helpers.truncate(helpers);
+        while self.helpers.len() < helpers {
+            let helper = Search::with_table(Arc::clone(&self.shared), Arc::clone(&self.table));
+            self.helpers.push(helper);
+        }
+    }
+    /// Replace the transposition table with an empty one of roughly `mb` megabytes
+    pub fn set_hash(&mut self, mb: usize) {
+        self.table = Arc::new(Table::new(mb));
+    }
+    /// the transposition table, clone it into other searches to share what they find
+    pub fn table(&self) -> &Arc<Table> {
+        &self.table
+    }
+    /// search with a table shared with other searches
+    pub fn set_table(&mut self, table: Arc<Table>) {
+        self.table = table;
+    }
+    /// the position that will be searched
+    pub fn position(&self) -> &Position {
+        &self.position
     }
-    pub fn set_position_direct(&mut self, board : &Board) {
-        self.board = *board;
-        self.my_side = board.side_to_move;
+    /// set the position directly, keeping its move history for repetition detection
+    pub fn set_position_direct(&mut self, position : &Position) {
+        self.position = position.clone();
+        self.my_side = position.board().side_to_move;
     }
-    /// initialize the board state using stuff
-    pub fn set_position(&mut self, input: String) {
+    /// initialize the board state using stuff, leaving the current position untouched if the fen or any move is invalid
+    pub fn set_position(&mut self, input: String) -> Result<(), PositionError> {
         let is_startpos = input.contains("startpos");
-        if is_startpos {
-            self.board = Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string());
+        let board = if is_startpos {
+            Board::new("x5o/7/7/7/7/7/o5x x 0 1".to_string())
         } else {
             let end_index = if input.contains("moves") {
                 input.find("moves").unwrap()
             } else {
                 input.len()
             };
-            let start_index = input.find("fen").unwrap() + 4;
-            self.board = Board::new(input[start_index..end_index].to_string());
-        }
+            let start_index = input.find("fen").map_or(input.len(), |index| index + 3);
+            Board::from_fen(input.get(start_index..end_index).unwrap_or(""))?
+        };
+        let mut position = Position::new(board);
         if input.contains("moves") {
             let begin_index = input.find("moves").unwrap() + 5;
-            let moves: Vec<&str> = input[begin_index..input.len()].split(' ').collect();
-            for mov in moves {
-                let other_pieces = self.board.other_pieces();
-                make_move(&mut self.board, &Move::from_str(mov, other_pieces));
+            for mov in input[begin_index..input.len()].split_whitespace() {
+                let mov = position.board().parse_move(mov)?;
+                position.make(&mov);
             }
         }
 
-        self.my_side = self.board.side_to_move;
+        self.my_side = position.board().side_to_move;
+        self.position = position;
+        Ok(())
     }
 
     /// find the best move for a position
     pub fn find_best_move(&mut self, print_info: bool, info: &GoInfo) -> (Move, i32){
+        // a stop that came in after the last search finished must not end this one
+        self.shared.stop.store(false, Ordering::Relaxed);
         // find run mode amongst : {infinite, time, depth, nodes, movetime}
         let end_cond;
         if info.infinite {
@@ -192,7 +331,7 @@ impl Search {
         } else if let Some(movetime) = info.movetime {
             end_cond = EndCondition::Time(Instant::now() + Duration::from_millis(movetime.into()));
         } else if let (Some(btime), Some(wtime)) = (info.btime, info.wtime) {
-            let (binc, winc) = if let (Some(binc), Some(winc)) = (info.binc, info.winc) {
+            let (_binc, _winc) = if let (Some(binc), Some(winc)) = (info.binc, info.winc) {
                 (binc, winc)
             } else {
                 (0, 0)
@@ -220,85 +359,168 @@ impl Search {
         }
         // store the start time of the search for nps calcs
         let t0 = Instant::now();
-        // store the best move
-        let mut bestmove = Move {
-            null: false,
-            from: 0,
-            to: 0,
-            capture_square: 0,
+        self.table.new_search();
+        self.shared.nodes.store(0, Ordering::Relaxed);
+
+        // the helpers search the same position until this thread is done, only sharing the table
+        let mut helpers = mem::take(&mut self.helpers);
+        for helper in &mut helpers {
+            helper.position = self.position.clone();
+            helper.my_side = self.my_side;
+            helper.table = Arc::clone(&self.table);
+            helper.tt_cutoffs = self.tt_cutoffs;
+        }
+        let helper_cond = match end_cond {
+            // the node limit is on the total, so any thread can be the one that reaches it
+            EndCondition::Nodes(nodes) => EndCondition::Nodes(nodes),
+            _ => EndCondition::Infinite,
         };
+        let result = thread::scope(|scope| {
+            for (id, helper) in helpers.iter_mut().enumerate() {
+                // every other helper starts a ply deeper so the threads dont all search in step
+                let first_depth = 1 + (id as u8 + 1) % 2;
+                let helper_cond = &helper_cond;
+                thread::Builder::new()
+                    .stack_size(SEARCH_STACK_SIZE)
+                    .spawn_scoped(scope, move || {
+                        helper.iterative_deepening(helper_cond, false, t0, first_depth)
+                    })
+                    .expect("unable to spawn a helper thread");
+            }
+            let result = self.iterative_deepening(&end_cond, print_info, t0, 1);
+            self.shared.stop.store(true, Ordering::Relaxed);
+            result
+        });
+        self.helpers = helpers;
+        self.shared.stop.store(false, Ordering::Relaxed);
+        result
+    }
+
+    /// The principal variation of the last search, best move first
+    pub fn pv(&self) -> &[Move] {
+        &self.best_pv
+    }
 
+    /// Nodes searched by every thread so far, exact for this thread but short by the batch each
+    /// running helper has not handed in yet, see [`NODE_FLUSH`]
+    fn total_nodes(&self) -> u64 {
+        self.shared.nodes.load(Ordering::Relaxed) + self.search_info.nodes % NODE_FLUSH
+    }
+
+    /// Search one ply deeper at a time from `first_depth` until the end condition or a stop
+    fn iterative_deepening(
+        &mut self,
+        end_cond: &EndCondition,
+        print_info: bool,
+        t0: Instant,
+        first_depth: u8,
+    ) -> (Move, i32) {
         self.search_info.reset();
+        self.best_pv.clear();
         let mut score=0;
-        for depth in 1..MAX_DEPTH {
-            score = self.negamax(&end_cond, -100_000, 100_000, depth);
-            let t1 = Instant::now();
-            if print_info {
-                println!(
-                    "info depth {depth} score {score}, nodes {}, time {}, tthits {}, cutoffs {}, nps {}",
-                    self.search_info.nodes,
-                    (t1 - t0).as_millis(), 
-                    self.search_info.tt_hits,
-                    self.search_info.cutoffs,
-                    (self.search_info.nodes as f64 / (t1 - t0).as_secs_f64()) as u64,
-                );
+        for depth in first_depth..MAX_DEPTH {
+            self.search_info.seldepth = 0;
+            self.negamax(end_cond, -INFINITY, INFINITY, depth, 0);
+            // an unfinished iteration is only used when there is nothing better, its score is that
+            // of the best root move searched so far rather than what the stopped search returned
+            let finished = !self.search_info.stopped;
+            if (finished || self.best_pv.is_empty()) && !self.pv[0].is_empty() {
+                self.best_pv.clone_from(&self.pv[0]);
+                score = self.search_info.root_score;
+                if print_info {
+                    let t1 = Instant::now();
+                    // can lag the helpers by a partial batch each, the final count after they stop is exact
+                    let nodes = self.total_nodes();
+                    let pv = self.best_pv.iter().map(Move::to_string).collect::<Vec<_>>();
+                    println!(
+                        "info depth {depth} seldepth {} score {}{} nodes {nodes} nps {} time {} hashfull {} pv {}",
+                        self.search_info.seldepth,
+                        uai_score(score),
+                        // the root moves not searched yet could only raise the score
+                        if finished { "" } else { " lowerbound" },
+                        (nodes as f64 / (t1 - t0).as_secs_f64()) as u64,
+                        (t1 - t0).as_millis(),
+                        self.table.hashfull(),
+                        pv.join(" "),
+                    );
+                }
             }
             
-            if end_cond.met(self.search_info.nodes, depth) || self.shared.lock().unwrap().stop {
-                self.shared.lock().unwrap().stop = false;
+            if self.search_info.stopped
+                || end_cond.met(self.total_nodes(), depth)
+                || self.shared.stop.load(Ordering::Relaxed)
+            {
                 break;
-            } else {
-                bestmove = self.stack_storage[depth as usize].pv_move.unwrap();
             }
         }
+        // hand in the nodes of the last unfinished batch
+        self.shared
+            .nodes
+            .fetch_add(self.search_info.nodes % NODE_FLUSH, Ordering::Relaxed);
 
-        (bestmove, score)
+        // stopped before a single root move was searched, any legal move beats none, and a pass
+        // is only left when the root has to pass or the game is already over. Without a search
+        // the score is the static one of the root
+        if self.best_pv.is_empty() {
+            let mut moves = MoveList::new();
+            generate_moves_into(self.position.board(), &mut moves);
+            self.best_pv.extend(moves.first().copied());
+            score = self.static_score(0);
+        }
+        (self.best_pv.first().copied().unwrap_or(Move::PASS), score)
     }
 
-    /// negamax
+    /// negamax, `ply` is the distance from the root
     pub fn negamax(
         &mut self,
         end_condition: &EndCondition,
         mut alpha: i32,
         beta: i32,
         depth: u8,
+        ply: u8,
     ) -> i32 {
-        // only worry about nodes cause search depth isnt useful here
-        if end_condition.met(self.search_info.nodes, 0) || self.shared.lock().unwrap().stop {
+        // only worry about nodes cause search depth isnt useful here, and count the nodes of every thread
+        if !self.search_info.stopped && end_condition.met(self.total_nodes(), 0) {
+            // stop the other threads right away rather than when this one gets back to the root
+            self.shared.stop.store(true, Ordering::Relaxed);
+        }
+        if self.search_info.stopped || self.shared.stop.load(Ordering::Relaxed) {
+            self.search_info.stopped = true;
             return 0;
         }
+        self.search_info.seldepth = self.search_info.seldepth.max(ply);
+        self.pv[ply as usize].clear();
 
-        if depth == 0 || self.board.game_over() {
-            return match self.board.status() {
-                Status::Draw => 0,
-                Status::Winner => 1000,
-                Status::Loser => -1000,
-                Status::Ongoing => self.eval.evaluate(&self.board),
-            };
+        if depth == 0 || self.position.game_over() {
+            return self.static_score(ply);
         }
 
         // probe tt
         let original_alpha = alpha;
-
-        let mut best_score = i32::MIN;
-        let mut best_move = Move {
-            null: false,
-            from: 0,
-            to: 0,
-            capture_square: 0,
-        };
-        let moves = generate_moves(&self.board);
-        let mut tt_move = None;
-        if let Some(entry) = &self.table[&self.board] {
-            if entry.hash == self.board.zobrist_hash() {
-                // tt move
-                if moves.contains(&entry.hash_move) {
-                    self.search_info.tt_hits += 1;
-                    tt_move = Some(entry.hash_move);
+        let mut hash_move = None;
+        if let Some(entry) = self.table.probe(self.position.board()) {
+            hash_move = Some(entry.hash_move);
+            // the root always searches so there is a move to play
+            if self.tt_cutoffs && ply > 0 && entry.depth >= depth {
+                let score = score_from_tt(entry.score, ply);
+                match entry.node_type {
+                    NodeType::Exact => return score,
+                    NodeType::Lower if score >= beta => return score,
+                    NodeType::Upper if score <= alpha => return score,
+                    _ => {}
                 }
             }
         }
 
+        let mut best_score = i32::MIN;
+        let mut best_move = Move::PASS;
+        let mut moves = MoveList::new();
+        generate_moves_into(self.position.board(), &mut moves);
+        // tt move
+        let tt_move = hash_move
+            .map(|hash_move| hash_move.unpack(self.position.board()))
+            .filter(|hash_move| moves.contains(hash_move));
+
         // killer move
         let mut killer_move = None;
         if let Some(killer_entry) = self.stack_storage[depth as usize].killer_move {
@@ -314,21 +536,35 @@ impl Search {
 
         let movepicker = MovePicker::new(moves, tt_move, killer_move);
 
-        let new_moves = movepicker.sort();
+        let new_moves = movepicker.sort(self.position.board());
 
         for mov in &new_moves {
             self.search_info.nodes += 1;
-            let delta = make_move(&mut self.board, mov);
-            let score = -self.negamax(end_condition, -beta, -alpha, depth - 1);
-            unmake_move(&mut self.board, mov, delta);
+            if self.search_info.nodes.is_multiple_of(NODE_FLUSH) {
+                self.shared.nodes.fetch_add(NODE_FLUSH, Ordering::Relaxed);
+            }
+            self.position.make(mov);
+            let score = -self.negamax(end_condition, -beta, -alpha, depth - 1, ply + 1);
+            self.position.unmake();
+            if self.search_info.stopped {
+                return 0;
+            }
 
             if score > best_score {
                 best_score = score;
 
                 best_move = *mov;
-                self.stack_storage[depth as usize].pv_move = Some(*mov);
                 if score > alpha {
                     alpha = score;
+                    // the line is this move followed by the best line of the child
+                    let (line, below) = self.pv.split_at_mut(ply as usize + 1);
+                    let line = &mut line[ply as usize];
+                    line.clear();
+                    line.push(*mov);
+                    line.extend_from_slice(&below[0]);
+                    if ply == 0 {
+                        self.search_info.root_score = score;
+                    }
                 }
             }
 
@@ -337,7 +573,6 @@ impl Search {
                 // if mov.capture_square == 0 {
                     self.stack_storage[depth as usize].killer_move = Some(*mov);
                 // }
-                self.search_info.cutoffs += 1;
                 break;
             }
         }
@@ -350,14 +585,219 @@ impl Search {
             NodeType::Exact
         };
 
-        self.table[&self.board] = Some(Entry::new(
-            self.board.zobrist_hash(),
-            best_move,
-            best_score,
+        let hash = self.position.board().zobrist_hash();
+        self.table.store(Entry::new(
+            hash,
+            PackedMove::from(best_move),
+            score_to_tt(best_score, ply),
             depth,
             node_type,
         ));
 
         best_score
     }
+
+    /// Score of the current position without searching, `ply` moves from the root
+    fn static_score(&self, ply: u8) -> i32 {
+        match self.position.status() {
+            Status::Draw => 0,
+            Status::Winner => WIN_SCORE - ply as i32,
+            Status::Loser => -(WIN_SCORE - ply as i32),
+            Status::Ongoing => self.eval.evaluate(self.position.board()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{atomic::Ordering, Arc},
+        thread,
+        time::Duration,
+    };
+
+    use game::movegen::generate_moves;
+
+    use super::{
+        score_from_tt, score_to_tt, uai_score, GoInfo, Search, Shared, MIN_WIN_SCORE, NODE_FLUSH,
+        WIN_SCORE,
+    };
+
+    const POSITIONS: [&str; 5] = [
+        "startpos",
+        "startpos moves f2 f6 g3 a2",
+        "fen x5o/7/2-1-2/7/2-1-2/7/o5x x 0 1",
+        "fen x5o/7/3-3/2---2/3-3/7/o5x x 0 1",
+        "fen 7/7/7/o6/ooo4/xxo4/xxx4 o 0 1",
+    ];
+
+    /// Search every position to a fixed depth, returning the best moves and the total nodes
+    fn search_all(tt_cutoffs: bool, depth: u32) -> (Vec<String>, u64) {
+        let mut best_moves = vec![];
+        let mut nodes = 0;
+        for position in POSITIONS {
+            let mut search = Search::new(Arc::new(Shared::default()));
+            search.tt_cutoffs = tt_cutoffs;
+            search.set_position(position.to_string()).unwrap();
+            let (best_move, score) =
+                search.find_best_move(false, &GoInfo::new(format!("depth {depth}")));
+            best_moves.push(format!("{best_move} {score}"));
+            nodes += search.search_info.nodes;
+        }
+        (best_moves, nodes)
+    }
+
+    #[test]
+    fn tt_cutoffs_save_nodes() {
+        let (with_cutoffs, nodes_with_cutoffs) = search_all(true, 5);
+        let (without_cutoffs, nodes_without_cutoffs) = search_all(false, 5);
+        assert_eq!(with_cutoffs, without_cutoffs);
+        assert!(
+            nodes_with_cutoffs < nodes_without_cutoffs,
+            "{nodes_with_cutoffs} >= {nodes_without_cutoffs}"
+        );
+    }
+
+    #[test]
+    fn lazy_smp_counts_every_thread() {
+        let mut search = Search::new(Arc::new(Shared::default()));
+        search.set_threads(4);
+        for position in POSITIONS {
+            search.set_position(position.to_string()).unwrap();
+            let (best_move, _) = search.find_best_move(false, &GoInfo::new("depth 4".to_string()));
+            assert!(generate_moves(search.position().board()).contains(&best_move));
+
+            let helper_nodes = search
+                .helpers
+                .iter()
+                .map(|helper| helper.search_info.nodes)
+                .sum::<u64>();
+            assert_eq!(
+                search.shared.nodes.load(Ordering::Relaxed),
+                search.search_info.nodes + helper_nodes
+            );
+            assert!(!search.shared.stop.load(Ordering::Relaxed));
+        }
+    }
+
+    #[test]
+    fn node_limit_counts_every_thread() {
+        const LIMIT: u64 = 20_000;
+        let mut search = Search::new(Arc::new(Shared::default()));
+        search.set_threads(4);
+        search.find_best_move(false, &GoInfo::new(format!("nodes {LIMIT}")));
+
+        // each helper can be a partial batch ahead of what the main thread saw when it stopped
+        let total = search.shared.nodes.load(Ordering::Relaxed);
+        assert!(total >= LIMIT, "{total}");
+        assert!(total < LIMIT + 4 * NODE_FLUSH, "{total}");
+    }
+
+    #[test]
+    fn stop_ends_every_thread() {
+        let shared = Arc::new(Shared::default());
+        let mut search = Search::new(Arc::clone(&shared));
+        search.set_threads(3);
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            shared.stop.store(true, Ordering::Relaxed);
+        });
+        let (best_move, _) = search.find_best_move(false, &GoInfo::new("infinite".to_string()));
+        stopper.join().unwrap();
+        assert!(generate_moves(search.position().board()).contains(&best_move));
+        assert!(!search.shared.stop.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn late_stop_is_ignored() {
+        let shared = Arc::new(Shared::default());
+        let mut search = Search::new(Arc::clone(&shared));
+        search.set_threads(2);
+        // a stop sent after the previous search already finished
+        shared.stop.store(true, Ordering::Relaxed);
+        let (best_move, _) = search.find_best_move(false, &GoInfo::new("depth 3".to_string()));
+        assert!(generate_moves(search.position().board()).contains(&best_move));
+        assert!(!search.pv().is_empty());
+    }
+
+    #[test]
+    fn stopped_search_plays_a_legal_move() {
+        let mut search = Search::new(Arc::new(Shared::default()));
+        let (best_move, _) = search.find_best_move(false, &GoInfo::new("nodes 1".to_string()));
+        assert!(generate_moves(search.position().board()).contains(&best_move));
+        assert_eq!(search.pv(), [best_move]);
+    }
+
+    #[test]
+    fn stopped_first_iteration_keeps_real_scores() {
+        let mut search = Search::new(Arc::new(Shared::default()));
+        search.set_position(POSITIONS[4].to_string()).unwrap();
+        let root = *search.position().board();
+        let (_, score) = search.find_best_move(false, &GoInfo::new("nodes 1".to_string()));
+        assert_eq!(score, search.eval.evaluate(&root));
+
+        // the limit hits during the third root move, the score is the best of the first two
+        let (best_move, score) = search.find_best_move(false, &GoInfo::new("nodes 3".to_string()));
+        let mut position = search.position().clone();
+        position.make(&best_move);
+        assert_eq!(score, -search.eval.evaluate(position.board()));
+        assert_ne!(score, 0);
+    }
+
+    #[test]
+    fn pv_is_playable() {
+        let mut search = Search::new(Arc::new(Shared::default()));
+        for position in POSITIONS {
+            search.set_position(position.to_string()).unwrap();
+            let (best_move, _) = search.find_best_move(false, &GoInfo::new("depth 5".to_string()));
+            let pv = search.pv().to_vec();
+            assert_eq!(pv.first(), Some(&best_move));
+            assert!(pv.len() <= 5);
+
+            let mut position = search.position().clone();
+            for mov in &pv {
+                assert!(generate_moves(position.board()).contains(mov), "{mov}");
+                position.make(mov);
+            }
+        }
+
+        // a capture of the last enemy stones is mate in one
+        search
+            .set_position("fen 7/7/7/7/7/1oo4/x1o4 x 0 1".to_string())
+            .unwrap();
+        let (best_move, score) = search.find_best_move(false, &GoInfo::new("depth 3".to_string()));
+        assert_eq!(best_move.to_string(), "b1");
+        assert_eq!(search.pv(), [best_move]);
+        assert_eq!(uai_score(score), "mate 1");
+    }
+
+    #[test]
+    fn uai_scores() {
+        assert_eq!(uai_score(0), "cp 0");
+        assert_eq!(uai_score(-37), "cp -37");
+        assert_eq!(uai_score(WIN_SCORE - 1), "mate 1");
+        assert_eq!(uai_score(WIN_SCORE - 3), "mate 2");
+        assert_eq!(uai_score(-(WIN_SCORE - 2)), "mate -1");
+        assert_eq!(uai_score(-(WIN_SCORE - 4)), "mate -2");
+    }
+
+    #[test]
+    fn win_scores_are_relative_to_the_node() {
+        // a win 3 plies below a node stored 5 plies from the root is a win 8 plies from the root
+        let stored = score_to_tt(WIN_SCORE - 8, 5);
+        assert_eq!(stored, WIN_SCORE - 3);
+        assert_eq!(score_from_tt(stored, 5), WIN_SCORE - 8);
+        // reached 2 plies from the root instead it is a win in 5
+        assert_eq!(score_from_tt(stored, 2), WIN_SCORE - 5);
+        assert_eq!(
+            score_from_tt(score_to_tt(-(WIN_SCORE - 8), 5), 2),
+            -(WIN_SCORE - 5)
+        );
+
+        // normal evaluations are left alone
+        for score in [0, 250, -250, MIN_WIN_SCORE - 1, -(MIN_WIN_SCORE - 1)] {
+            assert_eq!(score_to_tt(score, 7), score);
+            assert_eq!(score_from_tt(score, 7), score);
+        }
+    }
 }
@@ -1,13 +1,17 @@
-use game::movegen::Move;
+use game::{
+    board::Board,
+    movegen::{Move, MoveTag},
+    movelist::{MoveList, MAX_MOVES},
+};
 
 pub struct MovePicker {
-    moves: Vec<Move>,
+    moves: MoveList,
     hash_move: Option<Move>,
     killer_move: Option<Move>,
 }
 
 impl MovePicker {
-    pub fn new(moves: Vec<Move>, hash_move: Option<Move>, killer_move: Option<Move>) -> MovePicker {
+    pub fn new(moves: MoveList, hash_move: Option<Move>, killer_move: Option<Move>) -> MovePicker {
         MovePicker {
             moves,
             hash_move,
@@ -15,22 +19,21 @@ impl MovePicker {
         }
     }
 
-    pub fn sort(&self) -> Vec<Move> {
-        let mut out = vec![];
-        if let Some(killer) = self.killer_move {
-            out.push(killer);
-        }
-        if let Some(hash_move) = self.hash_move {
-            out.push(hash_move);
+    /// Order the moves in place: killer, then hash move, then the rest by captures and move type,
+    /// with equivalent and dominated doubles after the other moves
+    pub fn sort(mut self, board: &Board) -> MoveList {
+        let mut front = 0;
+        for special in [self.killer_move, self.hash_move].into_iter().flatten() {
+            if let Some(index) = self.moves[front..].iter().position(|x| *x == special) {
+                self.moves.swap(front, front + index);
+                front += 1;
+            }
         }
 
-        let mut new_moves = self
-            .moves
-            .iter()
-            .filter(|x| !out.contains(x))
-            .cloned()
-            .collect::<Vec<Move>>();
-        new_moves.sort_by_key(|x| {
+        // score every move once up front, tagging a move is too slow to redo on each comparison
+        let rest = &mut self.moves[front..];
+        let mut keyed = [(0, Move::PASS); MAX_MOVES];
+        for (slot, x) in keyed.iter_mut().zip(rest.iter()) {
             let mut key = 0;
 
             key += 8 - x.capture_square.count_ones() as i32;
@@ -38,10 +41,20 @@ impl MovePicker {
             if x.from != 0 {
                 key += 8
             }
-            key
-        });
-        out.append(&mut new_moves);
+            match x.tag(board) {
+                MoveTag::Unique => {}
+                MoveTag::Equivalent => key += 8,
+                MoveTag::Dominated => key += 16,
+            }
+            *slot = (key, *x);
+        }
+        let keyed = &mut keyed[..rest.len()];
+        // unstable sort so that ordering doesnt allocate
+        keyed.sort_unstable_by_key(|(key, _)| *key);
+        for (x, (_, mov)) in rest.iter_mut().zip(keyed.iter()) {
+            *x = *mov;
+        }
 
-        out
+        self.moves
     }
 }
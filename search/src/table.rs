@@ -1,47 +1,142 @@
-use std::ops::{Index, IndexMut};
+//! Transposition table that many searches can probe and store into at once.
+//!
+//! Every slot is two atomics, the entry packed into one `u64` and the position hash XORed with
+//! it in the other. Writes from two threads can interleave and leave a key from one entry with
+//! the data of another, but then the key no longer XORs back to the hash and the slot reads as a
+//! miss, so no locks are needed.
 
-use game::{board::Board, movegen::Move};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use game::{board::Board, movegen::PackedMove};
+
+/// Entries that share an index, the first slot prefers deep entries and the second always takes
+/// the newest one
+const BUCKET_SIZE: usize = 2;
+
+/// Buckets looked at to estimate how full the table is
+const HASHFULL_SAMPLE: usize = 1000;
+
+/// Generations wrap around at this, it has to fit in the bits [`Entry::pack`] leaves for it
+const GENERATIONS: u8 = 64;
+
+#[derive(Default)]
+struct Slot {
+    /// position hash XOR data
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+type Bucket = [Slot; BUCKET_SIZE];
 
 pub struct Table {
-    entries: Vec<Option<Entry>>,
+    buckets: Vec<Bucket>,
+    /// bumped at the start of every search so entries from old searches can be told apart
+    generation: AtomicU8,
 }
 
 impl Table {
-    pub fn new(size: usize) -> Table {
-        let mut entries = vec![];
-        for _ in 0..size {
-            entries.push(None);
+    /// Table using roughly `mb` megabytes
+    pub fn new(mb: usize) -> Table {
+        Table::with_buckets(mb * 1024 * 1024 / std::mem::size_of::<Bucket>())
+    }
+
+    fn with_buckets(buckets: usize) -> Table {
+        Table {
+            buckets: (0..buckets.max(1)).map(|_| Bucket::default()).collect(),
+            generation: AtomicU8::new(0),
         }
-        Table { entries: entries }
     }
 
-    pub fn reset(&mut self) {
-        self.entries.iter_mut().for_each(|x| *x = None);
+    /// Empty the table, this should not run while anything else uses it
+    pub fn reset(&self) {
+        for slot in self.buckets.iter().flatten() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+        self.generation.store(0, Ordering::Relaxed);
     }
-}
-impl IndexMut<&Board> for Table {
-    fn index_mut(&mut self, index: &Board) -> &mut Self::Output {
-        let num_entries = self.entries.len();
-        &mut self.entries[(index.zobrist_hash() % num_entries as u64) as usize]
+
+    /// Age every entry in the table by one search
+    pub fn new_search(&self) {
+        let generation = self.generation();
+        self.generation
+            .store((generation + 1) % GENERATIONS, Ordering::Relaxed);
+    }
+
+    fn generation(&self) -> u8 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    fn bucket(&self, hash: u64) -> &Bucket {
+        &self.buckets[(hash % self.buckets.len() as u64) as usize]
+    }
+
+    /// The entry stored for `board`, if there is one
+    pub fn probe(&self, board: &Board) -> Option<Entry> {
+        let hash = board.zobrist_hash();
+        self.bucket(hash).iter().find_map(|slot| {
+            let entry = slot.load()?;
+            (entry.hash == hash).then_some(entry)
+        })
+    }
+
+    /// Store `entry` in its bucket, over an older entry for the same position, else in the depth
+    /// preferred slot if that is empty, stale or not deeper, else in the always replace slot
+    pub fn store(&self, mut entry: Entry) {
+        let generation = self.generation();
+        entry.generation = generation;
+        let bucket = self.bucket(entry.hash);
+        let entries = bucket.each_ref().map(Slot::load);
+        let same = entries
+            .iter()
+            .position(|x| x.is_some_and(|x| x.hash == entry.hash));
+        let slot = same.unwrap_or(match entries[0] {
+            Some(old) if old.generation == generation && old.depth > entry.depth => 1,
+            _ => 0,
+        });
+        bucket[slot].save(&entry);
+    }
+
+    /// Permille of the table filled by the current search, estimated from the first buckets
+    pub fn hashfull(&self) -> usize {
+        let generation = self.generation();
+        let sample = &self.buckets[..self.buckets.len().min(HASHFULL_SAMPLE)];
+        let used = sample
+            .iter()
+            .flatten()
+            .filter_map(Slot::load)
+            .filter(|entry| entry.generation == generation)
+            .count();
+        used * 1000 / (sample.len() * BUCKET_SIZE)
     }
 }
 
-impl Index<&Board> for Table {
-    type Output = Option<Entry>;
+impl Slot {
+    /// The entry in the slot, none if it is empty or torn by two writes racing
+    fn load(&self) -> Option<Entry> {
+        let key = self.key.load(Ordering::Relaxed);
+        let data = self.data.load(Ordering::Relaxed);
+        Entry::unpack(key ^ data, data)
+    }
 
-    fn index(&self, index: &Board) -> &Self::Output {
-        &self.entries[(index.zobrist_hash() % self.entries.len() as u64) as usize]
+    fn save(&self, entry: &Entry) {
+        let data = entry.pack();
+        self.key.store(entry.hash ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Entry {
     pub hash: u64,
-    pub hash_move: Move,
+    pub hash_move: PackedMove,
     pub score: i32,
     pub depth: u8,
     pub node_type: NodeType,
+    /// the search that stored the entry, set by [`Table::store`]
+    generation: u8,
 }
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum NodeType {
     Upper,
     Lower,
@@ -49,13 +144,163 @@ pub enum NodeType {
 }
 
 impl Entry {
-    pub fn new(hash: u64, hash_move: Move, score: i32, depth: u8, node_type: NodeType) -> Entry {
+    pub fn new(
+        hash: u64,
+        hash_move: PackedMove,
+        score: i32,
+        depth: u8,
+        node_type: NodeType,
+    ) -> Entry {
         Entry {
             hash,
             hash_move,
             score,
             depth,
             node_type,
+            generation: 0,
+        }
+    }
+
+    /// Everything but the hash in one word: move in bits 0-15, score 16-47, depth 48-55, node
+    /// type 56-57 and generation 58-63
+    fn pack(&self) -> u64 {
+        self.hash_move.0 as u64
+            | (self.score as u32 as u64) << 16
+            | (self.depth as u64) << 48
+            | (self.node_type as u64) << 56
+            | (self.generation as u64) << 58
+    }
+
+    /// Inverse of [`Entry::pack`], depth 0 entries are never stored so they mark an empty slot
+    fn unpack(hash: u64, data: u64) -> Option<Entry> {
+        let depth = (data >> 48) as u8;
+        if depth == 0 {
+            return None;
+        }
+        let node_type = match (data >> 56) & 3 {
+            0 => NodeType::Upper,
+            1 => NodeType::Lower,
+            _ => NodeType::Exact,
+        };
+        Some(Entry {
+            hash,
+            hash_move: PackedMove(data as u16),
+            score: (data >> 16) as u32 as i32,
+            depth,
+            node_type,
+            generation: (data >> 58) as u8,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use game::movegen::PackedMove;
+
+    use super::{Entry, NodeType, Table};
+
+    fn entry(hash: u64, depth: u8) -> Entry {
+        Entry::new(hash, PackedMove(0), 0, depth, NodeType::Exact)
+    }
+
+    fn stored(table: &Table) -> Vec<Option<(u64, u8)>> {
+        table.buckets[0]
+            .iter()
+            .map(|slot| slot.load().map(|entry| (entry.hash, entry.depth)))
+            .collect()
+    }
+
+    #[test]
+    fn pack_round_trip() {
+        for score in [0, 1, -1, 999, -999, i32::MAX, i32::MIN] {
+            for node_type in [NodeType::Upper, NodeType::Lower, NodeType::Exact] {
+                let mut entry = Entry::new(0xdead_beef, PackedMove(0xabcd), score, 200, node_type);
+                entry.generation = 63;
+                assert_eq!(Entry::unpack(entry.hash, entry.pack()), Some(entry));
+            }
         }
     }
+
+    #[test]
+    fn replacement() {
+        let table = Table::with_buckets(1);
+        table.store(entry(1, 5));
+        assert_eq!(stored(&table), [Some((1, 5)), None]);
+
+        // shallower entries dont push out the deep one
+        table.store(entry(2, 3));
+        table.store(entry(3, 2));
+        assert_eq!(stored(&table), [Some((1, 5)), Some((3, 2))]);
+
+        // a position is only stored once, and deeper entries take the first slot
+        table.store(entry(3, 6));
+        assert_eq!(stored(&table), [Some((1, 5)), Some((3, 6))]);
+        table.store(entry(4, 7));
+        assert_eq!(stored(&table), [Some((4, 7)), Some((3, 6))]);
+
+        // old entries age out whatever their depth
+        table.new_search();
+        table.store(entry(5, 1));
+        assert_eq!(stored(&table), [Some((5, 1)), Some((3, 6))]);
+    }
+
+    #[test]
+    fn hashfull() {
+        let table = Table::with_buckets(10);
+        assert_eq!(table.hashfull(), 0);
+        for hash in 0..5 {
+            table.store(entry(hash, 1));
+        }
+        assert_eq!(table.hashfull(), 250);
+
+        table.new_search();
+        assert_eq!(table.hashfull(), 0);
+        table.store(entry(0, 1));
+        assert_eq!(table.hashfull(), 50);
+
+        table.reset();
+        assert_eq!(table.hashfull(), 0);
+    }
+
+    #[test]
+    fn concurrent_stores_never_tear() {
+        // the data of every entry follows from its hash, so a torn slot that got past the key
+        // check would show up as a mismatch
+        let expected = |hash: u64| {
+            Entry::new(
+                hash,
+                PackedMove(hash as u16),
+                hash as i32,
+                (hash % 100 + 1) as u8,
+                NodeType::Lower,
+            )
+        };
+        let hash = |n: u64| n.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let table = Table::with_buckets(4);
+        thread::scope(|scope| {
+            for thread in 0..4u64 {
+                let table = &table;
+                scope.spawn(move || {
+                    for i in 0..20_000u64 {
+                        table.store(expected(hash(i * 4 + thread)));
+                        // look up what this and the other threads stored recently
+                        for n in (i * 4).saturating_sub(16)..i * 4 + 4 {
+                            let hash = hash(n);
+                            let found = table
+                                .bucket(hash)
+                                .iter()
+                                .filter_map(|slot| slot.load())
+                                .find(|entry| entry.hash == hash);
+                            if let Some(mut found) = found {
+                                found.generation = 0;
+                                assert_eq!(found, expected(hash));
+                            }
+                        }
+                    }
+                });
+            }
+        });
+    }
 }